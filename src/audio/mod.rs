@@ -0,0 +1,128 @@
+#![allow(dead_code)]
+pub mod null;
+pub mod resampler;
+pub mod sdl;
+pub mod wav;
+
+use crate::core::apu::SAMPLE_RATE;
+use resampler::Resampler;
+
+/*
+APU samples (1 MiHz) -> Resampler, decimation by 8 (128 KiHz) -> Resampler, dynamic rate -> output
+
+Dynamic rate control: the emulator is paced by the video (59.73 Hz, or the monitor refresh rate),
+not by the sound card clock. The second stage ratio is nudged by up to MAX_RATE_DELTA to keep
+the output queue half full, so audio doesn't crackle (queue runs dry) or drift (queue grows).
+*/
+
+pub const OUTPUT_SAMPLE_RATE: u32 = 48000;
+
+const DECIMATION: u32 = 8;
+const DECIMATION_TAPS: usize = 64;
+const RESAMPLER_TAPS: usize = 64;
+const CUTOFF_HZ: f64 = 21000.0;
+
+const TARGET_LATENCY_SECS: f64 = 0.06;
+const MAX_RATE_DELTA: f64 = 0.005;
+
+pub trait AudioOutput: Send {
+	fn sample_rate(&self) -> u32;
+	// Stereo frames waiting to be played, None when the backend doesn't play them
+	fn queued_frames(&self) -> Option<usize>;
+	fn queue(&mut self, samples: &[f32]);
+
+	// Backends that throw the samples away, the resampling is skipped for them
+	fn is_null(&self) -> bool {
+		false
+	}
+}
+
+// Both resampling stages, from the APU rate to `output_rate`
+struct Converter {
+	decimator: Resampler,
+	resampler: Resampler,
+	nominal_ratio: f64,
+	decimated: Vec<f32>,
+}
+
+impl Converter {
+	fn new(output_rate: u32) -> Self {
+		let input_rate = SAMPLE_RATE as f64;
+		let decimated_rate = input_rate / DECIMATION as f64;
+		let output_rate = output_rate as f64;
+		let nominal_ratio = decimated_rate / output_rate;
+
+		// The first stage only has to remove what would alias into the audible band,
+		// the second one keeps the passband below the output Nyquist frequency
+		let cutoff = CUTOFF_HZ.min(output_rate * 0.44);
+
+		Self {
+			decimator: Resampler::new(DECIMATION as f64, 0.5 / DECIMATION as f64, DECIMATION_TAPS),
+			resampler: Resampler::new(nominal_ratio, cutoff / decimated_rate, RESAMPLER_TAPS),
+			nominal_ratio,
+			decimated: Vec::new(),
+		}
+	}
+
+	// `rate_delta` speeds up (negative) or slows down (positive) the output, 0.0 is the nominal rate
+	fn process(&mut self, samples: &[f32], rate_delta: f64, output: &mut Vec<f32>) {
+		self.resampler.ratio = self.nominal_ratio * (1.0 + rate_delta);
+
+		self.decimated.clear();
+		self.decimator.process(samples, &mut self.decimated);
+		self.resampler.process(&self.decimated, output);
+	}
+}
+
+pub struct Audio {
+	output: Box<dyn AudioOutput>,
+	converter: Converter,
+	target_frames: usize, // queued frames the rate control aims for
+	resampled: Vec<f32>,
+}
+
+impl Audio {
+	pub fn new(output: Box<dyn AudioOutput>) -> Self {
+		let output_rate = output.sample_rate();
+
+		Self {
+			converter: Converter::new(output_rate),
+			target_frames: (output_rate as f64 * TARGET_LATENCY_SECS) as usize,
+			resampled: Vec::new(),
+			output,
+		}
+	}
+
+	pub fn null() -> Self {
+		Self::new(Box::new(null::NullAudio))
+	}
+
+	// Resamples interleaved stereo APU samples and sends them to the output
+	pub fn push(&mut self, samples: &[f32]) {
+		if self.output.is_null() {
+			return;
+		}
+
+		let queued = self.output.queued_frames();
+
+		// Way behind the video (e.g. a 144 Hz monitor), drop the samples instead of adding latency
+		if queued.is_some_and(|queued| queued > self.target_frames * 4) {
+			return;
+		}
+
+		let rate_delta = match queued {
+			Some(queued) => {
+				let fill = (queued as f64 / (self.target_frames * 2) as f64).min(1.0);
+				MAX_RATE_DELTA * (2.0 * fill - 1.0)
+			}
+			None => 0.0,
+		};
+
+		self.resampled.clear();
+		self
+			.converter
+			.process(samples, rate_delta, &mut self.resampled);
+
+		self.output.queue(&self.resampled);
+	}
+}
@@ -0,0 +1,20 @@
+use super::{AudioOutput, OUTPUT_SAMPLE_RATE};
+
+// Discards every sample, used for headless runs and when no audio device is available
+pub struct NullAudio;
+
+impl AudioOutput for NullAudio {
+	fn sample_rate(&self) -> u32 {
+		OUTPUT_SAMPLE_RATE
+	}
+
+	fn queued_frames(&self) -> Option<usize> {
+		None
+	}
+
+	fn queue(&mut self, _samples: &[f32]) {}
+
+	fn is_null(&self) -> bool {
+		true
+	}
+}
@@ -0,0 +1,101 @@
+use std::f64::consts::PI;
+
+const PHASES: usize = 256; // kernel table resolution between two input samples
+
+/*
+Windowed sinc (Blackman) resampler for interleaved stereo samples.
+`ratio` is the number of input frames consumed per output frame, it can be changed
+between calls while the cutoff stays fixed.
+*/
+#[derive(Debug, Clone)]
+pub struct Resampler {
+	pub ratio: f64,
+	taps: usize,
+	kernel: Vec<f32>,     // (PHASES + 1) rows of `taps` coefficients
+	input: Vec<[f32; 2]>, // frames not consumed yet
+	position: f64,        // next output position in `input`
+}
+
+impl Resampler {
+	// `cutoff` in cycles per input sample (0.5 is the input Nyquist frequency)
+	pub fn new(ratio: f64, cutoff: f64, taps: usize) -> Self {
+		let half = taps / 2;
+		let mut kernel = Vec::with_capacity((PHASES + 1) * taps);
+
+		for phase in 0..=PHASES {
+			let fraction = phase as f64 / PHASES as f64;
+			let row: Vec<f64> = (0..taps)
+				.map(|tap| {
+					let distance = fraction + (half - 1) as f64 - tap as f64;
+					sinc(2.0 * cutoff * distance) * blackman(distance / half as f64)
+				})
+				.collect();
+
+			// Unity gain at DC for every phase
+			let sum: f64 = row.iter().sum();
+			kernel.extend(row.iter().map(|coefficient| (coefficient / sum) as f32));
+		}
+
+		Self {
+			ratio,
+			taps,
+			kernel,
+			input: vec![[0.0; 2]; half],
+			position: (half - 1) as f64,
+		}
+	}
+
+	// Resamples `samples` and appends the result to `output`
+	pub fn process(&mut self, samples: &[f32], output: &mut Vec<f32>) {
+		self
+			.input
+			.extend(samples.chunks_exact(2).map(|frame| [frame[0], frame[1]]));
+
+		let half = self.taps / 2;
+
+		while self.position as usize + half < self.input.len() {
+			let index = self.position as usize;
+			let fraction = self.position - index as f64;
+			let phase = (fraction * PHASES as f64).round() as usize;
+
+			let coefficients = &self.kernel[phase * self.taps..(phase + 1) * self.taps];
+			let frames = &self.input[index + 1 - half..=index + half];
+
+			let mut left = 0.0;
+			let mut right = 0.0;
+			for (frame, coefficient) in frames.iter().zip(coefficients) {
+				left += frame[0] * coefficient;
+				right += frame[1] * coefficient;
+			}
+
+			output.push(left);
+			output.push(right);
+			self.position += self.ratio;
+		}
+
+		// Keep only the frames the next outputs still need
+		let consumed = (self.position as usize + 1)
+			.saturating_sub(half)
+			.min(self.input.len());
+		self.input.drain(..consumed);
+		self.position -= consumed as f64;
+	}
+}
+
+fn sinc(x: f64) -> f64 {
+	if x == 0.0 {
+		return 1.0;
+	}
+
+	(PI * x).sin() / (PI * x)
+}
+
+// `x` from -1.0 to 1.0
+fn blackman(x: f64) -> f64 {
+	if x.abs() >= 1.0 {
+		return 0.0;
+	}
+
+	let n = (x + 1.0) / 2.0;
+	0.42 - 0.5 * (2.0 * PI * n).cos() + 0.08 * (4.0 * PI * n).cos()
+}
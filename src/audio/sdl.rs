@@ -0,0 +1,106 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::time::Duration;
+
+use sdl2::audio::{AudioQueue, AudioSpecDesired};
+
+use super::{AudioOutput, OUTPUT_SAMPLE_RATE};
+
+const DEVICE_BUFFER_FRAMES: u16 = 1024;
+const QUEUE_POLL_INTERVAL: Duration = Duration::from_millis(2);
+
+/*
+Plays the samples through an SDL2 audio queue.
+SDL objects can't leave the thread that created them, so the queue lives on its own thread
+and receives the samples through a channel.
+*/
+pub struct SdlAudio {
+	sample_sender: Sender<Vec<f32>>,
+	queued_frames: Arc<AtomicUsize>,
+	sample_rate: u32,
+}
+
+impl SdlAudio {
+	pub fn new() -> Result<Self, String> {
+		let (sample_sender, sample_receiver) = channel();
+		let (init_sender, init_receiver) = channel();
+		let queued_frames = Arc::new(AtomicUsize::new(0));
+		let thread_queued_frames = Arc::clone(&queued_frames);
+
+		std::thread::spawn(move || {
+			let queue = match open_queue() {
+				Ok(queue) => queue,
+				Err(e) => {
+					let _ = init_sender.send(Err(e));
+					return;
+				}
+			};
+
+			let _ = init_sender.send(Ok(queue.spec().freq as u32));
+			run_queue(queue, sample_receiver, thread_queued_frames);
+		});
+
+		let sample_rate = init_receiver
+			.recv()
+			.map_err(|_| "Audio thread stopped".to_string())??;
+
+		Ok(Self {
+			sample_sender,
+			queued_frames,
+			sample_rate,
+		})
+	}
+}
+
+impl AudioOutput for SdlAudio {
+	fn sample_rate(&self) -> u32 {
+		self.sample_rate
+	}
+
+	fn queued_frames(&self) -> Option<usize> {
+		Some(self.queued_frames.load(Ordering::Relaxed))
+	}
+
+	fn queue(&mut self, samples: &[f32]) {
+		let _ = self.sample_sender.send(samples.to_vec());
+	}
+}
+
+fn open_queue() -> Result<AudioQueue<f32>, String> {
+	let subsystem = sdl2::init()?.audio()?;
+
+	let desired = AudioSpecDesired {
+		freq: Some(OUTPUT_SAMPLE_RATE as i32),
+		channels: Some(2),
+		samples: Some(DEVICE_BUFFER_FRAMES),
+	};
+
+	let queue = subsystem.open_queue::<f32, _>(None, &desired)?;
+	queue.resume();
+
+	Ok(queue)
+}
+
+// Runs until the SdlAudio (the sender) is dropped
+fn run_queue(
+	queue: AudioQueue<f32>,
+	receiver: Receiver<Vec<f32>>,
+	queued_frames: Arc<AtomicUsize>,
+) {
+	loop {
+		match receiver.recv_timeout(QUEUE_POLL_INTERVAL) {
+			Ok(samples) => {
+				if let Err(e) = queue.queue_audio(&samples) {
+					log::warn!("Failed to queue audio: {}", e);
+				}
+			}
+			Err(RecvTimeoutError::Timeout) => (),
+			Err(RecvTimeoutError::Disconnected) => break,
+		}
+
+		// size() is in bytes, 2 channels of f32
+		let frames = queue.size() as usize / (2 * std::mem::size_of::<f32>());
+		queued_frames.store(frames, Ordering::Relaxed);
+	}
+}
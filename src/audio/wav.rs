@@ -0,0 +1,123 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use super::Converter;
+
+pub const WAV_SAMPLE_RATE: u32 = 48000;
+
+const CHANNELS: u16 = 2;
+const BITS_PER_SAMPLE: u16 = 16;
+const HEADER_SIZE: u32 = 44;
+const MAX_DATA_SIZE: u32 = u32::MAX - (HEADER_SIZE - 8); // the RIFF size field is 32 bits (~6 hours)
+
+/*
++--------+------+-------------------------------------------+
+| Offset | Size | Field                                     |
++--------+------+-------------------------------------------+
+| 0      | 4    | "RIFF"                                    |
+| 4      | 4    | File size - 8                             |
+| 8      | 4    | "WAVE"                                    |
+| 12     | 4    | "fmt "                                    |
+| 16     | 4    | 16 (fmt chunk size)                       |
+| 20     | 2    | 1 (PCM)                                   |
+| 22     | 2    | Channels                                  |
+| 24     | 4    | Sample rate                               |
+| 28     | 4    | Byte rate                                 |
+| 32     | 2    | Block align (bytes per frame)             |
+| 34     | 2    | Bits per sample                           |
+| 36     | 4    | "data"                                    |
+| 40     | 4    | Data size                                 |
++--------+------+-------------------------------------------+
+*/
+
+/*
+Records the APU output to a 16-bit stereo WAV file.
+It resamples at a fixed rate, without the playback rate control, so the same
+emulated samples always produce the same file.
+*/
+pub struct WavRecorder {
+	writer: BufWriter<File>,
+	converter: Converter,
+	resampled: Vec<f32>,
+	data_size: u32,
+}
+
+impl WavRecorder {
+	pub fn create(path: &Path) -> io::Result<Self> {
+		let mut writer = BufWriter::new(File::create(path)?);
+		write_header(&mut writer, 0)?;
+
+		Ok(Self {
+			writer,
+			converter: Converter::new(WAV_SAMPLE_RATE),
+			resampled: Vec::new(),
+			data_size: 0,
+		})
+	}
+
+	// Interleaved stereo APU samples
+	pub fn write(&mut self, samples: &[f32]) -> io::Result<()> {
+		self.resampled.clear();
+		self.converter.process(samples, 0.0, &mut self.resampled);
+
+		let data_size = (self.resampled.len() as u32)
+			.checked_mul(BITS_PER_SAMPLE as u32 / 8)
+			.and_then(|size| self.data_size.checked_add(size))
+			.filter(|size| *size <= MAX_DATA_SIZE)
+			.ok_or_else(|| io::Error::other("WAV files are limited to 4 GiB"))?;
+
+		for sample in &self.resampled {
+			let sample = (sample.clamp(-1.0, 1.0) * i16::MAX as f32).round() as i16;
+			self.writer.write_all(&sample.to_le_bytes())?;
+		}
+		self.data_size = data_size;
+
+		Ok(())
+	}
+
+	// Fills in the chunk sizes, the file is complete after this
+	pub fn finish(mut self) -> io::Result<()> {
+		self.writer.seek(SeekFrom::Start(0))?;
+		write_header(&mut self.writer, self.data_size)?;
+		self.writer.flush()
+	}
+}
+
+fn write_header(writer: &mut impl Write, data_size: u32) -> io::Result<()> {
+	let block_align = CHANNELS * BITS_PER_SAMPLE / 8;
+
+	writer.write_all(b"RIFF")?;
+	writer.write_all(&(HEADER_SIZE - 8 + data_size).to_le_bytes())?;
+	writer.write_all(b"WAVE")?;
+
+	writer.write_all(b"fmt ")?;
+	writer.write_all(&16u32.to_le_bytes())?;
+	writer.write_all(&1u16.to_le_bytes())?;
+	writer.write_all(&CHANNELS.to_le_bytes())?;
+	writer.write_all(&WAV_SAMPLE_RATE.to_le_bytes())?;
+	writer.write_all(&(WAV_SAMPLE_RATE * block_align as u32).to_le_bytes())?;
+	writer.write_all(&block_align.to_le_bytes())?;
+	writer.write_all(&BITS_PER_SAMPLE.to_le_bytes())?;
+
+	writer.write_all(b"data")?;
+	writer.write_all(&data_size.to_le_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn stops_at_the_riff_size_limit() {
+		let path = std::env::temp_dir().join(format!("emuchan_wav_limit_{}.wav", std::process::id()));
+		let mut recorder = WavRecorder::create(&path).unwrap();
+		recorder.data_size = MAX_DATA_SIZE - 4;
+
+		assert!(recorder.write(&[0.0; 4096]).is_err());
+		assert_eq!(recorder.data_size, MAX_DATA_SIZE - 4);
+		recorder.finish().unwrap();
+
+		let _ = std::fs::remove_file(path);
+	}
+}
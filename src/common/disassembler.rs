@@ -95,6 +95,11 @@ impl fmt::Display for Instruction {
 	}
 }
 
+// Instruction table bundled into the binary, so it works from any working directory
+pub fn load_instructions() -> InstructionBank {
+	serde_json::from_str(include_str!("instructions.json")).expect("Unable to parse data.")
+}
+
 pub fn parse_from_file(location: &str) -> InstructionBank {
 	let file = File::open(location).expect("Unable to read file.");
 	let buffer = BufReader::new(file);
@@ -115,16 +120,17 @@ pub fn parse_from_file(location: &str) -> InstructionBank {
 //   return buffer;
 // }
 
+// Returns None when the instruction runs past the end of `bytes`
 pub fn decode(
 	mut addr: usize,
 	bytes: &[u8],
 	instruction: &InstructionBank,
-) -> (usize, Instruction) {
-	let opcode = bytes[addr];
+) -> Option<(usize, Instruction)> {
+	let opcode = *bytes.get(addr)?;
 	addr += 1;
 
 	let meta_intruction = if opcode == 0xCB {
-		let opc = bytes[addr];
+		let opc = *bytes.get(addr)?;
 		addr += 1;
 
 		&instruction.cbprefixed[&Opcode(opc)]
@@ -132,6 +138,16 @@ pub fn decode(
 		&instruction.unprefixed[&Opcode(opcode)]
 	};
 
+	let operand_bytes: usize = meta_intruction
+		.operands
+		.iter()
+		.map(|o| o.bytes.unwrap_or(0) as usize)
+		.sum();
+
+	if addr + operand_bytes > bytes.len() {
+		return None;
+	}
+
 	let decoded_operands = meta_intruction
 		.operands
 		.iter()
@@ -177,7 +193,7 @@ pub fn decode(
 		flags: meta_intruction.flags.clone(),
 	};
 
-	return (addr, decoded_instruction);
+	return Some((addr, decoded_instruction));
 }
 
 pub fn disassemble(
@@ -189,7 +205,9 @@ pub fn disassemble(
 	let mut addr = star_addr;
 
 	for _ in 0..amount_of_instruction {
-		let (new_addr, instruction) = decode(addr, bytes, instructions);
+		let Some((new_addr, instruction)) = decode(addr, bytes, instructions) else {
+			break;
+		};
 
 		println!("0x{:04X} {}", addr, instruction);
 		addr = new_addr;
@@ -1,2 +1,3 @@
 pub mod boot;
 pub mod disassembler;
+pub mod serde_base64;
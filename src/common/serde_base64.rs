@@ -0,0 +1,25 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+// Byte arrays and vectors as base64 strings, JSON number arrays are ~4 times bigger.
+// Used with #[serde(with = "serde_base64")], it also covers arrays serde doesn't (over 32 elements)
+pub fn serialize<S, T>(bytes: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+	S: Serializer,
+	T: AsRef<[u8]>,
+{
+	serializer.serialize_str(&STANDARD.encode(bytes))
+}
+
+pub fn deserialize<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+	D: Deserializer<'de>,
+	T: TryFrom<Vec<u8>>,
+{
+	let encoded = String::deserialize(deserializer)?;
+	let bytes = STANDARD.decode(encoded).map_err(D::Error::custom)?;
+	let length = bytes.len();
+
+	T::try_from(bytes).map_err(|_| D::Error::custom(format!("unexpected length of {} bytes", length)))
+}
@@ -0,0 +1,3 @@
+pub mod noise;
+pub mod square;
+pub mod wave;
@@ -0,0 +1,138 @@
+use serde::{Deserialize, Serialize};
+
+use crate::core::apu::common::envelope::Envelope;
+use crate::core::apu::common::length::Length;
+
+/*
++------+------------------------------------------------------+
+| Addr | Notes                                                |
++------+------------------------------------------------------+
+| FF20 | NR41 Bits 0-5: length                                |
+| FF21 | NR42 volume envelope                                 |
+| FF22 | NR43 Bits 4-7: clock shift, Bit 3: 7-bit LFSR,       |
+|      |      Bits 0-2: divisor code                          |
+| FF23 | NR44 Bit 7: trigger, Bit 6: length enable            |
++------+------------------------------------------------------+
+*/
+
+const DIVISORS: [u32; 8] = [8, 16, 32, 48, 64, 80, 96, 112];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Noise {
+	pub enabled: bool,
+	pub length: Length,
+	pub envelope: Envelope,
+	pub register: u8, // NR43
+	lfsr: u16,        // 15-bit linear feedback shift register
+	timer: u32,
+}
+
+impl Noise {
+	pub fn new() -> Self {
+		Self {
+			enabled: false,
+			length: Length::new(64),
+			envelope: Envelope::new(),
+			register: 0x00,
+			lfsr: 0x7FFF,
+			timer: 0,
+		}
+	}
+
+	pub fn read(&self, register: u16) -> u8 {
+		match register {
+			2 => self.envelope.register,
+			3 => self.register,
+			4 => ((self.length.enabled as u8) << 6) | 0xBF,
+			_ => 0xFF,
+		}
+	}
+
+	pub fn write(&mut self, register: u16, data: u8, clocks_next: bool) {
+		match register {
+			1 => self.length.load(data & 0x3F),
+			2 => {
+				self.envelope.register = data;
+				if !self.envelope.dac_enabled() {
+					self.enabled = false;
+				}
+			}
+			3 => self.register = data,
+			4 => {
+				if self.length.write_control(data, clocks_next) {
+					self.enabled = false;
+				}
+
+				if data & 0x80 != 0 {
+					self.trigger();
+				}
+			}
+			_ => (),
+		}
+	}
+
+	fn trigger(&mut self) {
+		self.enabled = self.envelope.dac_enabled();
+		self.lfsr = 0x7FFF;
+		self.timer = self.period();
+		self.envelope.trigger();
+	}
+
+	// One T-cycle
+	pub fn step(&mut self) {
+		if self.timer > 0 {
+			self.timer -= 1;
+		}
+
+		if self.timer == 0 {
+			self.timer = self.period();
+
+			// Shifts 14 and 15 stop the LFSR
+			if self.register >> 4 < 14 {
+				self.clock_lfsr();
+			}
+		}
+	}
+
+	fn clock_lfsr(&mut self) {
+		let xor = (self.lfsr & 0x01) ^ ((self.lfsr >> 1) & 0x01);
+		self.lfsr = (self.lfsr >> 1) | (xor << 14);
+
+		// 7-bit mode also feeds bit 6
+		if self.register & 0x08 != 0 {
+			self.lfsr = (self.lfsr & !(1 << 6)) | (xor << 6);
+		}
+	}
+
+	pub fn clock_length(&mut self) {
+		if self.length.clock() {
+			self.enabled = false;
+		}
+	}
+
+	pub fn clock_envelope(&mut self) {
+		self.envelope.clock();
+	}
+
+	pub fn dac_enabled(&self) -> bool {
+		self.envelope.dac_enabled()
+	}
+
+	// Digital output (0-15)
+	pub fn output(&self) -> u8 {
+		if !self.enabled || self.lfsr & 0x01 != 0 {
+			return 0;
+		}
+
+		self.envelope.volume
+	}
+
+	// LFSR clock rate
+	pub fn frequency_hz(&self) -> f32 {
+		4194304.0 / self.period() as f32
+	}
+
+	fn period(&self) -> u32 {
+		DIVISORS[(self.register & 0x07) as usize] << (self.register >> 4)
+	}
+}
@@ -0,0 +1,176 @@
+use serde::{Deserialize, Serialize};
+
+use crate::core::apu::common::envelope::Envelope;
+use crate::core::apu::common::length::Length;
+use crate::core::apu::common::sweep::Sweep;
+
+/*
++------+------+------------------------------------------------------+
+| CH1  | CH2  | Notes                                                |
++------+------+------------------------------------------------------+
+| FF10 |  --  | NR10 sweep (CH1 only)                                |
+| FF11 | FF16 | NRx1 Bits 6-7: duty, Bits 0-5: length                |
+| FF12 | FF17 | NRx2 volume envelope                                 |
+| FF13 | FF18 | NRx3 lower 8 bits of the frequency (write only)      |
+| FF14 | FF19 | NRx4 Bit 7: trigger, Bit 6: length enable,           |
+|      |      |      Bits 0-2: upper 3 bits of the frequency         |
++------+------+------------------------------------------------------+
+*/
+
+const DUTY_TABLE: [[u8; 8]; 4] = [
+	[0, 0, 0, 0, 0, 0, 0, 1], // 12.5%
+	[1, 0, 0, 0, 0, 0, 0, 1], // 25%
+	[1, 0, 0, 0, 0, 1, 1, 1], // 50%
+	[0, 1, 1, 1, 1, 1, 1, 0], // 75%
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Square {
+	pub enabled: bool,
+	pub sweep: Option<Sweep>,
+	pub length: Length,
+	pub envelope: Envelope,
+	pub duty: u8,
+	pub frequency: u16, // 11 bits
+	timer: u16,
+	duty_step: u8,
+}
+
+impl Square {
+	pub fn new(has_sweep: bool) -> Self {
+		Self {
+			enabled: false,
+			sweep: if has_sweep { Some(Sweep::new()) } else { None },
+			length: Length::new(64),
+			envelope: Envelope::new(),
+			duty: 0,
+			frequency: 0,
+			timer: 0,
+			duty_step: 0,
+		}
+	}
+
+	pub fn read(&self, register: u16) -> u8 {
+		match register {
+			0 => match &self.sweep {
+				Some(sweep) => sweep.register | 0x80,
+				None => 0xFF,
+			},
+			1 => (self.duty << 6) | 0x3F,
+			2 => self.envelope.register,
+			4 => ((self.length.enabled as u8) << 6) | 0xBF,
+			_ => 0xFF,
+		}
+	}
+
+	// `clocks_next`: the next frame sequencer step clocks the length counters
+	pub fn write(&mut self, register: u16, data: u8, clocks_next: bool) {
+		match register {
+			0 => {
+				if let Some(sweep) = &mut self.sweep {
+					if sweep.write(data) {
+						self.enabled = false;
+					}
+				}
+			}
+			1 => {
+				self.duty = data >> 6;
+				self.length.load(data & 0x3F);
+			}
+			2 => {
+				self.envelope.register = data;
+				if !self.envelope.dac_enabled() {
+					self.enabled = false;
+				}
+			}
+			3 => self.frequency = (self.frequency & 0x700) | data as u16,
+			4 => {
+				self.frequency = (self.frequency & 0xFF) | ((data as u16 & 0x07) << 8);
+
+				if self.length.write_control(data, clocks_next) {
+					self.enabled = false;
+				}
+
+				if data & 0x80 != 0 {
+					self.trigger();
+				}
+			}
+			_ => (),
+		}
+	}
+
+	fn trigger(&mut self) {
+		self.enabled = self.envelope.dac_enabled();
+		self.timer = self.period();
+		self.envelope.trigger();
+
+		if let Some(sweep) = &mut self.sweep {
+			if sweep.trigger(self.frequency) {
+				self.enabled = false;
+			}
+		}
+	}
+
+	// Frequency timer, one T-cycle
+	pub fn step(&mut self) {
+		if self.timer > 0 {
+			self.timer -= 1;
+		}
+
+		if self.timer == 0 {
+			self.timer = self.period();
+			self.duty_step = (self.duty_step + 1) & 0x07;
+		}
+	}
+
+	pub fn clock_length(&mut self) {
+		if self.length.clock() {
+			self.enabled = false;
+		}
+	}
+
+	pub fn clock_envelope(&mut self) {
+		self.envelope.clock();
+	}
+
+	pub fn clock_sweep(&mut self) {
+		let Some(sweep) = &mut self.sweep else {
+			return;
+		};
+
+		let (frequency, disable) = sweep.clock();
+
+		if let Some(frequency) = frequency {
+			self.frequency = frequency;
+		}
+
+		if disable {
+			self.enabled = false;
+		}
+	}
+
+	pub fn reset_duty(&mut self) {
+		self.duty_step = 0;
+	}
+
+	pub fn dac_enabled(&self) -> bool {
+		self.envelope.dac_enabled()
+	}
+
+	// Digital output (0-15)
+	pub fn output(&self) -> u8 {
+		if !self.enabled {
+			return 0;
+		}
+
+		DUTY_TABLE[self.duty as usize][self.duty_step as usize] * self.envelope.volume
+	}
+
+	pub fn frequency_hz(&self) -> f32 {
+		131072.0 / (2048 - self.frequency) as f32
+	}
+
+	fn period(&self) -> u16 {
+		(2048 - self.frequency) * 4
+	}
+}
@@ -0,0 +1,172 @@
+use serde::{Deserialize, Serialize};
+
+use crate::core::apu::common::length::Length;
+
+/*
++------+------------------------------------------------------+
+| Addr | Notes                                                |
++------+------------------------------------------------------+
+| FF1A | NR30 Bit 7: DAC enable                               |
+| FF1B | NR31 length (8 bits)                                 |
+| FF1C | NR32 Bits 5-6: volume (mute, 100%, 50%, 25%)         |
+| FF1D | NR33 lower 8 bits of the frequency (write only)      |
+| FF1E | NR34 Bit 7: trigger, Bit 6: length enable,           |
+|      |      Bits 0-2: upper 3 bits of the frequency         |
+| FF30 | Wave RAM, 32 4-bit samples, upper nibble first       |
++------+------------------------------------------------------+
+*/
+
+const TRIGGER_DELAY_CYCLES: u16 = 6; // the first sample is read 3 wave clocks late
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Wave {
+	pub enabled: bool,
+	pub dac_enabled: bool,
+	pub length: Length,
+	pub volume: u8, // NR32 bits 5-6
+	pub frequency: u16,
+	pub ram: [u8; 16],
+	timer: u16,
+	position: u8,
+	sample_buffer: u8,
+	just_read: bool, // wave RAM was read by the channel in the last cycle
+}
+
+impl Wave {
+	pub fn new() -> Self {
+		Self {
+			enabled: false,
+			dac_enabled: false,
+			length: Length::new(256),
+			volume: 0,
+			frequency: 0,
+			ram: [0; 16],
+			timer: 0,
+			position: 0,
+			sample_buffer: 0,
+			just_read: false,
+		}
+	}
+
+	pub fn read(&self, register: u16) -> u8 {
+		match register {
+			0 => ((self.dac_enabled as u8) << 7) | 0x7F,
+			2 => (self.volume << 5) | 0x9F,
+			4 => ((self.length.enabled as u8) << 6) | 0xBF,
+			_ => 0xFF,
+		}
+	}
+
+	pub fn write(&mut self, register: u16, data: u8, clocks_next: bool) {
+		match register {
+			0 => {
+				self.dac_enabled = data & 0x80 != 0;
+				if !self.dac_enabled {
+					self.enabled = false;
+				}
+			}
+			1 => self.length.load(data),
+			2 => self.volume = (data >> 5) & 0x03,
+			3 => self.frequency = (self.frequency & 0x700) | data as u16,
+			4 => {
+				self.frequency = (self.frequency & 0xFF) | ((data as u16 & 0x07) << 8);
+
+				if self.length.write_control(data, clocks_next) {
+					self.enabled = false;
+				}
+
+				if data & 0x80 != 0 {
+					self.trigger();
+				}
+			}
+			_ => (),
+		}
+	}
+
+	// While the channel plays, the CPU only reaches the byte the channel is reading (DMG)
+	pub fn read_ram(&self, addr: u16) -> u8 {
+		if !self.enabled {
+			return self.ram[addr as usize & 0x0F];
+		}
+
+		if self.just_read {
+			self.ram[self.position as usize / 2]
+		} else {
+			0xFF
+		}
+	}
+
+	pub fn write_ram(&mut self, addr: u16, data: u8) {
+		if !self.enabled {
+			self.ram[addr as usize & 0x0F] = data;
+		} else if self.just_read {
+			self.ram[self.position as usize / 2] = data;
+		}
+	}
+
+	fn trigger(&mut self) {
+		// Retriggering right as a sample is read corrupts the first bytes of wave RAM (DMG)
+		if self.enabled && self.timer == 2 {
+			let index = (self.position as usize).div_ceil(2) & 0x0F;
+
+			if index < 4 {
+				self.ram[0] = self.ram[index];
+			} else {
+				let block = index & !0x03;
+				self.ram.copy_within(block..block + 4, 0);
+			}
+		}
+
+		self.enabled = self.dac_enabled;
+		self.position = 0;
+		self.timer = self.period() + TRIGGER_DELAY_CYCLES;
+	}
+
+	// Frequency timer, one T-cycle
+	pub fn step(&mut self) {
+		self.just_read = false;
+
+		if self.timer > 0 {
+			self.timer -= 1;
+		}
+
+		if self.timer == 0 {
+			self.timer = self.period();
+			self.position = (self.position + 1) & 0x1F;
+
+			let byte = self.ram[self.position as usize / 2];
+			self.sample_buffer = if self.position & 1 == 0 { byte >> 4 } else { byte & 0x0F };
+			self.just_read = true;
+		}
+	}
+
+	pub fn clock_length(&mut self) {
+		if self.length.clock() {
+			self.enabled = false;
+		}
+	}
+
+	pub fn reset_sample(&mut self) {
+		self.sample_buffer = 0;
+	}
+
+	// Digital output (0-15)
+	pub fn output(&self) -> u8 {
+		if !self.enabled {
+			return 0;
+		}
+
+		match self.volume {
+			0 => 0,
+			volume => self.sample_buffer >> (volume - 1),
+		}
+	}
+
+	pub fn frequency_hz(&self) -> f32 {
+		65536.0 / (2048 - self.frequency) as f32
+	}
+
+	fn period(&self) -> u16 {
+		(2048 - self.frequency) * 2
+	}
+}
@@ -0,0 +1,60 @@
+use serde::{Deserialize, Serialize};
+
+/*
+NRx2 - Volume envelope (clocked at 64 Hz)
+	Bits 4-7: initial volume, Bit 3: direction (1 = up), Bits 0-2: period (0 = no change)
+The DAC is on while any of bits 3-7 is set.
+*/
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Envelope {
+	pub register: u8,
+	pub volume: u8,
+	timer: u8,
+}
+
+impl Envelope {
+	pub fn new() -> Self {
+		Self {
+			register: 0x00,
+			volume: 0,
+			timer: 0,
+		}
+	}
+
+	pub fn dac_enabled(&self) -> bool {
+		self.register & 0xF8 != 0
+	}
+
+	pub fn trigger(&mut self) {
+		self.volume = self.register >> 4;
+		self.timer = self.period();
+	}
+
+	pub fn clock(&mut self) {
+		if self.register & 0x07 == 0 {
+			return;
+		}
+
+		if self.timer > 0 {
+			self.timer -= 1;
+		}
+
+		if self.timer == 0 {
+			self.timer = self.period();
+
+			if self.register & 0x08 != 0 && self.volume < 15 {
+				self.volume += 1;
+			} else if self.register & 0x08 == 0 && self.volume > 0 {
+				self.volume -= 1;
+			}
+		}
+	}
+
+	// A period of 0 reloads the timer with 8
+	fn period(&self) -> u8 {
+		match self.register & 0x07 {
+			0 => 8,
+			period => period,
+		}
+	}
+}
@@ -0,0 +1,62 @@
+use serde::{Deserialize, Serialize};
+
+// Length counter, silences the channel when it reaches zero (clocked at 256 Hz)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Length {
+	pub counter: u16,
+	pub enabled: bool, // NRx4 bit 6
+	max: u16,          // 64, or 256 for the wave channel
+}
+
+impl Length {
+	pub fn new(max: u16) -> Self {
+		Self {
+			counter: 0,
+			enabled: false,
+			max,
+		}
+	}
+
+	// NRx1, the counter counts up from the written value
+	pub fn load(&mut self, data: u8) {
+		self.counter = self.max - (data as u16 & (self.max - 1));
+	}
+
+	// Returns true when the channel must be disabled
+	pub fn clock(&mut self) -> bool {
+		if self.enabled && self.counter > 0 {
+			self.counter -= 1;
+			return self.counter == 0;
+		}
+
+		false
+	}
+
+	/*
+	NRx4 write. `clocks_next` tells if the next frame sequencer step clocks the length,
+	when it doesn't, enabling the length takes an extra clock, and so does a trigger that reloads it.
+	Returns true when the channel must be disabled.
+	*/
+	pub fn write_control(&mut self, data: u8, clocks_next: bool) -> bool {
+		let was_enabled = self.enabled;
+		let trigger = data & 0x80 != 0;
+		self.enabled = data & 0x40 != 0;
+
+		let mut disable = false;
+
+		if !was_enabled && self.enabled && !clocks_next && self.counter > 0 {
+			self.counter -= 1;
+			disable = self.counter == 0 && !trigger;
+		}
+
+		if trigger && self.counter == 0 {
+			self.counter = self.max;
+
+			if self.enabled && !clocks_next {
+				self.counter -= 1;
+			}
+		}
+
+		disable
+	}
+}
@@ -0,0 +1,3 @@
+pub mod envelope;
+pub mod length;
+pub mod sweep;
@@ -0,0 +1,98 @@
+use serde::{Deserialize, Serialize};
+
+/*
+NR10 - Frequency sweep of CH1 (clocked at 128 Hz)
+	Bits 4-6: period, Bit 3: direction (1 = down), Bits 0-2: shift
+*/
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Sweep {
+	pub register: u8,
+	pub enabled: bool,
+	pub shadow: u16, // copy of the frequency the sweep works on
+	timer: u8,
+	negate_used: bool, // a subtraction happened since the last trigger
+}
+
+impl Sweep {
+	pub fn new() -> Self {
+		Self {
+			register: 0x00,
+			enabled: false,
+			shadow: 0,
+			timer: 0,
+			negate_used: false,
+		}
+	}
+
+	// Returns true when the channel must be disabled
+	pub fn write(&mut self, data: u8) -> bool {
+		let disable = self.negate_used && self.register & 0x08 != 0 && data & 0x08 == 0;
+		self.register = data & 0x7F;
+
+		// Leaving negate mode after a subtraction disables the channel
+		disable
+	}
+
+	// Returns true when the channel must be disabled (the overflow check failed)
+	pub fn trigger(&mut self, frequency: u16) -> bool {
+		self.shadow = frequency;
+		self.timer = self.period();
+		self.negate_used = false;
+		self.enabled = self.register & 0x70 != 0 || self.shift() != 0;
+
+		self.shift() != 0 && self.calculate() > 2047
+	}
+
+	// Returns the new frequency (if changed) and whether the channel must be disabled
+	pub fn clock(&mut self) -> (Option<u16>, bool) {
+		if self.timer > 0 {
+			self.timer -= 1;
+		}
+
+		if self.timer != 0 {
+			return (None, false);
+		}
+		self.timer = self.period();
+
+		if !self.enabled || self.register & 0x70 == 0 {
+			return (None, false);
+		}
+
+		let frequency = self.calculate();
+		if frequency > 2047 {
+			return (None, true);
+		}
+
+		if self.shift() == 0 {
+			return (None, false);
+		}
+
+		self.shadow = frequency;
+
+		// The new frequency goes through the overflow check again
+		(Some(frequency), self.calculate() > 2047)
+	}
+
+	fn calculate(&mut self) -> u16 {
+		let delta = self.shadow >> self.shift();
+
+		if self.register & 0x08 != 0 {
+			self.negate_used = true;
+			self.shadow - delta
+		} else {
+			self.shadow + delta
+		}
+	}
+
+	fn shift(&self) -> u8 {
+		self.register & 0x07
+	}
+
+	// A period of 0 reloads the timer with 8
+	fn period(&self) -> u8 {
+		match (self.register >> 4) & 0x07 {
+			0 => 8,
+			period => period,
+		}
+	}
+}
@@ -0,0 +1,531 @@
+mod channel;
+mod common;
+
+use channel::noise::Noise;
+use channel::square::Square;
+use channel::wave::Wave;
+use serde::{Deserialize, Serialize};
+
+/*
++-----------+------+------------------------------------------------------+
+| Addr      | Name | Notes                                                |
++-----------+------+------------------------------------------------------+
+| FF10-FF14 | CH1  | Square with frequency sweep                          |
+| FF16-FF19 | CH2  | Square                                               |
+| FF1A-FF1E | CH3  | Wave                                                 |
+| FF20-FF23 | CH4  | Noise                                                |
+| FF24      | NR50 | Bits 4-6: left volume, Bits 0-2: right volume        |
+| FF25      | NR51 | Bits 4-7: CH4-CH1 on the left, Bits 0-3: on the right|
+| FF26      | NR52 | Bit 7: power, Bits 0-3: CH4-CH1 on (read only)       |
+| FF30-FF3F | Wave | Wave RAM                                             |
++-----------+------+------------------------------------------------------+
+
+Frame sequencer, clocked by the falling edge of bit 12 of the system counter (DIV bit 4, 512 Hz):
+	Step:     0   1   2   3   4   5   6   7
+	Length:   x       x       x       x
+	Sweep:            x               x
+	Envelope:                             x
+*/
+
+pub const SAMPLE_RATE: u32 = 1048576; // one sample per M-cycle
+const CPU_CLOCK: u32 = 4194304;
+const CYCLES_PER_SAMPLE: u32 = CPU_CLOCK / SAMPLE_RATE;
+const MAX_BUFFERED_SAMPLES: usize = SAMPLE_RATE as usize; // half a second of stereo samples
+
+const DIV_APU_BIT: u16 = 1 << 12;
+
+pub const SCOPE_LENGTH: usize = 1024; // points kept per channel for the oscilloscope
+const SCOPE_DECIMATION: u32 = 32; // one point every 32 samples (32768 Hz)
+
+// Bits that always read as 1, FF10-FF2F
+const READ_MASKS: [u8; 0x20] = [
+	0x80, 0x3F, 0x00, 0xFF, 0xBF, // NR10-NR14
+	0xFF, 0x3F, 0x00, 0xFF, 0xBF, // NR20-NR24
+	0x7F, 0xFF, 0x9F, 0xFF, 0xBF, // NR30-NR34
+	0xFF, 0xFF, 0x00, 0x00, 0xBF, // NR40-NR44
+	0x00, 0x00, 0x70, // NR50-NR52
+	0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, // Unused
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct APU {
+	pub enabled: bool, // NR52 bit 7
+	pub ch1: Square,
+	pub ch2: Square,
+	pub ch3: Wave,
+	pub ch4: Noise,
+	pub nr50: u8,
+	pub nr51: u8,
+	frame_step: u8, // next frame sequencer step
+	div_bit: bool,  // last value of the system counter bit that clocks the frame sequencer
+	caught_up: u32, // T-cycles already run by catch_up, skipped by the next steps
+
+	#[serde(skip)]
+	pub samples: Vec<f32>, // interleaved stereo (left, right) at SAMPLE_RATE, all channels
+	#[serde(skip)]
+	pub playback_samples: Vec<f32>, // same as `samples` with mute and solo applied
+	sample_cycles: u32,
+	capacitor_left: f32, // high-pass filter that removes the DAC DC offset
+	capacitor_right: f32,
+	capacitor_charge: f32,
+	#[serde(skip)]
+	playback_capacitor_left: f32,
+	#[serde(skip)]
+	playback_capacitor_right: f32,
+
+	#[serde(skip)]
+	pub mute: [bool; 4],
+	#[serde(skip)]
+	pub solo: [bool; 4], // mute and solo only apply to `playback_samples`
+	#[serde(skip, default = "empty_scope")]
+	scope: [[f32; SCOPE_LENGTH]; 4],
+	scope_position: usize,
+	scope_samples: u32,
+}
+
+// Snapshot of a channel for the debugger
+#[derive(Debug, Clone)]
+pub struct ChannelState {
+	pub enabled: bool,
+	pub dac_enabled: bool,
+	pub frequency: u16, // 11-bit register value, NR43 for the noise channel
+	pub frequency_hz: f32,
+	pub volume: u8,       // envelope volume, NR32 volume code for the wave channel
+	pub duty: Option<u8>, // square channels
+	pub length: u16,      // length counter, remaining clocks
+	pub length_enabled: bool,
+}
+
+impl APU {
+	pub fn new() -> Self {
+		Self {
+			enabled: false,
+			ch1: Square::new(true),
+			ch2: Square::new(false),
+			ch3: Wave::new(),
+			ch4: Noise::new(),
+			nr50: 0x00,
+			nr51: 0x00,
+			frame_step: 0,
+			div_bit: false,
+			caught_up: 0,
+			samples: Vec::new(),
+			playback_samples: Vec::new(),
+			sample_cycles: 0,
+			capacitor_left: 0.0,
+			capacitor_right: 0.0,
+			capacitor_charge: 0.999958f32.powi(CYCLES_PER_SAMPLE as i32),
+			playback_capacitor_left: 0.0,
+			playback_capacitor_right: 0.0,
+			mute: [false; 4],
+			solo: [false; 4],
+			scope: [[0.0; SCOPE_LENGTH]; 4],
+			scope_position: 0,
+			scope_samples: 0,
+		}
+	}
+
+	pub fn read(&self, addr: u16) -> u8 {
+		let value = match addr {
+			0xFF10..=0xFF14 => self.ch1.read(addr - 0xFF10),
+			0xFF15..=0xFF19 => self.ch2.read(addr - 0xFF15),
+			0xFF1A..=0xFF1E => self.ch3.read(addr - 0xFF1A),
+			0xFF1F..=0xFF23 => self.ch4.read(addr - 0xFF1F),
+			0xFF24 => self.nr50,
+			0xFF25 => self.nr51,
+			0xFF26 => {
+				((self.enabled as u8) << 7)
+					| ((self.ch4.enabled as u8) << 3)
+					| ((self.ch3.enabled as u8) << 2)
+					| ((self.ch2.enabled as u8) << 1)
+					| (self.ch1.enabled as u8)
+			}
+			0xFF30..=0xFF3F => return self.ch3.read_ram(addr),
+			_ => 0xFF,
+		};
+
+		value | READ_MASKS[(addr - 0xFF10) as usize]
+	}
+
+	pub fn write(&mut self, addr: u16, data: u8) {
+		if addr == 0xFF26 {
+			self.set_power(data & 0x80 != 0);
+			return;
+		}
+
+		if (0xFF30..=0xFF3F).contains(&addr) {
+			self.ch3.write_ram(addr, data);
+			return;
+		}
+
+		// While powered off only the length counters can be written (DMG)
+		if !self.enabled {
+			match addr {
+				0xFF11 => self.ch1.length.load(data & 0x3F),
+				0xFF16 => self.ch2.length.load(data & 0x3F),
+				0xFF1B => self.ch3.length.load(data),
+				0xFF20 => self.ch4.length.load(data & 0x3F),
+				_ => (),
+			}
+			return;
+		}
+
+		let clocks_next = self.frame_step.is_multiple_of(2);
+
+		match addr {
+			0xFF10..=0xFF14 => self.ch1.write(addr - 0xFF10, data, clocks_next),
+			0xFF15..=0xFF19 => self.ch2.write(addr - 0xFF15, data, clocks_next),
+			0xFF1A..=0xFF1E => self.ch3.write(addr - 0xFF1A, data, clocks_next),
+			0xFF1F..=0xFF23 => self.ch4.write(addr - 0xFF1F, data, clocks_next),
+			0xFF24 => self.nr50 = data,
+			0xFF25 => self.nr51 = data,
+			_ => (),
+		}
+	}
+
+	fn set_power(&mut self, on: bool) {
+		if on == self.enabled {
+			return;
+		}
+
+		if on {
+			// The frame sequencer restarts at step 0, the duty and wave positions are reset
+			self.frame_step = 0;
+			self.ch1.reset_duty();
+			self.ch2.reset_duty();
+			self.ch3.reset_sample();
+		} else {
+			// Every register is cleared, except the length counters (DMG) and wave RAM
+			let mut ch1 = Square::new(true);
+			let mut ch2 = Square::new(false);
+			let mut ch3 = Wave::new();
+			let mut ch4 = Noise::new();
+
+			ch1.length.counter = self.ch1.length.counter;
+			ch2.length.counter = self.ch2.length.counter;
+			ch3.length.counter = self.ch3.length.counter;
+			ch4.length.counter = self.ch4.length.counter;
+			ch3.ram = self.ch3.ram;
+
+			self.ch1 = ch1;
+			self.ch2 = ch2;
+			self.ch3 = ch3;
+			self.ch4 = ch4;
+			self.nr50 = 0x00;
+			self.nr51 = 0x00;
+		}
+
+		self.enabled = on;
+	}
+
+	// One T-cycle, `div_counter` is the timer system counter
+	pub fn step(&mut self, div_counter: u16) {
+		if self.caught_up > 0 {
+			self.caught_up -= 1;
+			return;
+		}
+
+		self.tick(div_counter);
+	}
+
+	// The CPU accesses the APU in the middle of an instruction, but the APU is only stepped after it.
+	// Runs it `cycles` T-cycles into the instruction first, so wave RAM access and retrigger timing
+	// are seen on the right cycle. `div_counter` is the system counter when the instruction started.
+	pub fn catch_up(&mut self, cycles: u32, div_counter: u16) {
+		while self.caught_up < cycles {
+			self.caught_up += 1;
+			self.tick(div_counter.wrapping_add(self.caught_up as u16));
+		}
+	}
+
+	fn tick(&mut self, div_counter: u16) {
+		let div_bit = div_counter & DIV_APU_BIT != 0;
+
+		if self.enabled {
+			if self.div_bit && !div_bit {
+				self.step_frame_sequencer();
+			}
+
+			self.ch1.step();
+			self.ch2.step();
+			self.ch3.step();
+			self.ch4.step();
+		}
+		self.div_bit = div_bit;
+
+		self.step_sample();
+	}
+
+	fn step_frame_sequencer(&mut self) {
+		if self.frame_step.is_multiple_of(2) {
+			self.ch1.clock_length();
+			self.ch2.clock_length();
+			self.ch3.clock_length();
+			self.ch4.clock_length();
+		}
+
+		if self.frame_step == 2 || self.frame_step == 6 {
+			self.ch1.clock_sweep();
+		}
+
+		if self.frame_step == 7 {
+			self.ch1.clock_envelope();
+			self.ch2.clock_envelope();
+			self.ch4.clock_envelope();
+		}
+
+		self.frame_step = (self.frame_step + 1) & 0x07;
+	}
+
+	fn step_sample(&mut self) {
+		self.sample_cycles += 1;
+
+		if self.sample_cycles < CYCLES_PER_SAMPLE {
+			return;
+		}
+		self.sample_cycles = 0;
+
+		let (left, right) = self.mix([true; 4]);
+		let left = high_pass(&mut self.capacitor_left, self.capacitor_charge, left);
+		let right = high_pass(&mut self.capacitor_right, self.capacitor_charge, right);
+
+		// Mute and solo only change what is played, recordings always get every channel
+		let audible = self.audible_channels();
+		let (playback_left, playback_right) = if audible == [true; 4] {
+			self.playback_capacitor_left = self.capacitor_left;
+			self.playback_capacitor_right = self.capacitor_right;
+			(left, right)
+		} else {
+			let (playback_left, playback_right) = self.mix(audible);
+			(
+				high_pass(&mut self.playback_capacitor_left, self.capacitor_charge, playback_left),
+				high_pass(&mut self.playback_capacitor_right, self.capacitor_charge, playback_right),
+			)
+		};
+
+		self.step_scope();
+
+		// Nobody is taking the samples, drop the oldest half at once
+		if self.samples.len() >= MAX_BUFFERED_SAMPLES {
+			self.samples.drain(..MAX_BUFFERED_SAMPLES / 2);
+			self.playback_samples.drain(..MAX_BUFFERED_SAMPLES / 2);
+		}
+		self.samples.push(left);
+		self.samples.push(right);
+		self.playback_samples.push(playback_left);
+		self.playback_samples.push(playback_right);
+	}
+
+	fn step_scope(&mut self) {
+		self.scope_samples += 1;
+
+		if self.scope_samples < SCOPE_DECIMATION {
+			return;
+		}
+		self.scope_samples = 0;
+
+		let outputs = [
+			self.ch1.output(),
+			self.ch2.output(),
+			self.ch3.output(),
+			self.ch4.output(),
+		];
+
+		for (channel, output) in outputs.iter().enumerate() {
+			self.scope[channel][self.scope_position] = *output as f32 / 15.0;
+		}
+		self.scope_position = (self.scope_position + 1) % SCOPE_LENGTH;
+	}
+
+	// Digital output of a channel (0.0 to 1.0), oldest point first
+	pub fn scope(&self, channel: usize) -> Vec<f32> {
+		let scope = &self.scope[channel];
+
+		scope[self.scope_position..]
+			.iter()
+			.chain(&scope[..self.scope_position])
+			.copied()
+			.collect()
+	}
+
+	pub fn channel_state(&self, channel: usize) -> ChannelState {
+		match channel {
+			0 | 1 => {
+				let square = if channel == 0 { &self.ch1 } else { &self.ch2 };
+
+				ChannelState {
+					enabled: square.enabled,
+					dac_enabled: square.dac_enabled(),
+					frequency: square.frequency,
+					frequency_hz: square.frequency_hz(),
+					volume: square.envelope.volume,
+					duty: Some(square.duty),
+					length: square.length.counter,
+					length_enabled: square.length.enabled,
+				}
+			}
+			2 => ChannelState {
+				enabled: self.ch3.enabled,
+				dac_enabled: self.ch3.dac_enabled,
+				frequency: self.ch3.frequency,
+				frequency_hz: self.ch3.frequency_hz(),
+				volume: self.ch3.volume,
+				duty: None,
+				length: self.ch3.length.counter,
+				length_enabled: self.ch3.length.enabled,
+			},
+			_ => ChannelState {
+				enabled: self.ch4.enabled,
+				dac_enabled: self.ch4.dac_enabled(),
+				frequency: self.ch4.register as u16,
+				frequency_hz: self.ch4.frequency_hz(),
+				volume: self.ch4.envelope.volume,
+				duty: None,
+				length: self.ch4.length.counter,
+				length_enabled: self.ch4.length.enabled,
+			},
+		}
+	}
+
+	// Channels heard through the speakers, when any channel is soloed only the soloed ones are
+	fn audible_channels(&self) -> [bool; 4] {
+		if self.solo.iter().any(|solo| *solo) {
+			return self.solo;
+		}
+
+		self.mute.map(|mute| !mute)
+	}
+
+	// Returns the left and right output of the `audible` channels, -1.0 to 1.0
+	fn mix(&self, audible: [bool; 4]) -> (f32, f32) {
+		if !self.enabled {
+			return (0.0, 0.0);
+		}
+
+		let outputs = [
+			dac(self.ch1.dac_enabled(), self.ch1.output()),
+			dac(self.ch2.dac_enabled(), self.ch2.output()),
+			dac(self.ch3.dac_enabled, self.ch3.output()),
+			dac(self.ch4.dac_enabled(), self.ch4.output()),
+		];
+
+		let mut left = 0.0;
+		let mut right = 0.0;
+
+		for (channel, output) in outputs.iter().enumerate() {
+			if !audible[channel] {
+				continue;
+			}
+
+			if self.nr51 & (0x10 << channel) != 0 {
+				left += output;
+			}
+			if self.nr51 & (0x01 << channel) != 0 {
+				right += output;
+			}
+		}
+
+		let left_volume = (((self.nr50 >> 4) & 0x07) + 1) as f32 / 8.0;
+		let right_volume = ((self.nr50 & 0x07) + 1) as f32 / 8.0;
+
+		(left * left_volume / 4.0, right * right_volume / 4.0)
+	}
+}
+
+fn empty_scope() -> [[f32; SCOPE_LENGTH]; 4] {
+	[[0.0; SCOPE_LENGTH]; 4]
+}
+
+// High-pass filter step, `capacitor` keeps the filter state between samples
+fn high_pass(capacitor: &mut f32, charge: f32, input: f32) -> f32 {
+	let output = input - *capacitor;
+	*capacitor = input - output * charge;
+	output
+}
+
+// Digital 0-15 to analog, a disabled DAC outputs nothing
+fn dac(enabled: bool, output: u8) -> f32 {
+	if !enabled {
+		return 0.0;
+	}
+
+	output as f32 / 7.5 - 1.0
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::core::bus::BUS;
+	use crate::core::timer::Timer;
+	use std::sync::{Arc, Mutex};
+
+	// Wave channel at the highest frequency (a sample every 2 T-cycles), first read 8 T-cycles after the trigger
+	fn start_wave(write: &mut dyn FnMut(u16, u8)) {
+		write(0xFF26, 0x80);
+		for addr in 0xFF30..=0xFF3F {
+			write(addr, (addr as u8) << 4);
+		}
+		write(0xFF1A, 0x80);
+		write(0xFF1C, 0x20);
+		write(0xFF1D, 0xFF);
+		write(0xFF1E, 0x87);
+	}
+
+	#[test]
+	fn catch_up_matches_stepping_after_the_instruction() {
+		let mut stepped = APU::new();
+		let mut caught_up = APU::new();
+		start_wave(&mut |addr, data| stepped.write(addr, data));
+		start_wave(&mut |addr, data| caught_up.write(addr, data));
+
+		// 12 T-cycle instructions, one of them accesses the APU on its third M-cycle
+		let mut counter: u16 = 0;
+		for _ in 0..10000 {
+			caught_up.catch_up(8, counter);
+			for _ in 0..12 {
+				counter = counter.wrapping_add(1);
+				stepped.step(counter);
+				caught_up.step(counter);
+			}
+		}
+
+		assert_eq!(stepped.samples, caught_up.samples);
+		assert_eq!(stepped.read(0xFF26), caught_up.read(0xFF26));
+	}
+
+	#[test]
+	fn wave_ram_is_only_reachable_on_the_read_cycle() {
+		let mut apu = APU::new();
+		start_wave(&mut |addr, data| apu.write(addr, data));
+
+		for _ in 0..7 {
+			apu.step(0);
+		}
+		assert_eq!(apu.read(0xFF30), 0xFF);
+
+		apu.step(0);
+		assert_eq!(apu.read(0xFF35), 0x00, "reads the byte the channel is playing");
+	}
+
+	#[test]
+	fn cpu_accesses_catch_the_apu_up() {
+		let apu = Arc::new(Mutex::new(APU::new()));
+		let mut bus = BUS::new();
+		bus.apu_connect(Arc::clone(&apu));
+		bus.timer_connect(Arc::new(Mutex::new(Timer::new())));
+		start_wave(&mut |addr, data| bus.write(addr, data));
+
+		// Two M-cycles (e.g. the opcode and operand fetches), then the wave RAM read
+		bus.start_instruction();
+		bus.cpu_read(0xC000);
+		bus.cpu_read(0xC001);
+		assert_eq!(bus.cpu_read(0xFF30), 0x00);
+
+		// The frame loop skips the 8 T-cycles the APU already ran
+		let mut apu = apu.lock().unwrap();
+		for _ in 0..12 {
+			apu.step(0);
+		}
+		assert_eq!(apu.samples.len(), 2 * 12 / CYCLES_PER_SAMPLE as usize);
+	}
+}
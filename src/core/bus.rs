@@ -1,10 +1,17 @@
 #![allow(dead_code)]
 use log::debug;
+use serde::{Deserialize, Serialize};
 
 use std::sync::{Arc, Mutex};
 
+use crate::common::serde_base64;
+use crate::core::apu::APU;
 use crate::core::cartridge::Cartridge;
+use crate::core::dma::DMA;
+use crate::core::interrupt::Interrupt;
+use crate::core::joypad::Joypad;
 use crate::core::ppu::PPU;
+use crate::core::timer::Timer;
 
 /*
 +-------+-------+---------------------------------+-----------------------------------------------------+
@@ -26,12 +33,26 @@ use crate::core::ppu::PPU;
 
 */
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BUS {
+	#[serde(with = "serde_base64")]
 	pub memory: [u8; 0x10000], // address 0 to 0xffff
+	#[serde(skip)]
 	pub cartridge: Option<Arc<Mutex<Cartridge>>>,
+	#[serde(skip)]
 	pub ppu: Option<Arc<Mutex<PPU>>>,
+	#[serde(skip)]
+	pub interrupt: Option<Arc<Mutex<Interrupt>>>,
+	#[serde(skip)]
+	pub timer: Option<Arc<Mutex<Timer>>>,
+	#[serde(skip)]
+	pub joypad: Option<Arc<Mutex<Joypad>>>,
+	#[serde(skip)]
+	pub apu: Option<Arc<Mutex<APU>>>,
+	pub dma: DMA,
 	pub disable_boot: bool,
+	#[serde(skip)]
+	access_cycles: u32, // T-cycles of the current CPU instruction before the next access
 }
 
 impl BUS {
@@ -40,11 +61,62 @@ impl BUS {
 			memory: [0; 0x10000],
 			cartridge: None,
 			ppu: None,
+			interrupt: None,
+			timer: None,
+			joypad: None,
+			apu: None,
+			dma: DMA::new(),
 			disable_boot: false,
+			access_cycles: 0,
+		}
+	}
+
+	// Called by the CPU before an instruction (or interrupt dispatch) starts accessing memory
+	pub fn start_instruction(&mut self) {
+		self.access_cycles = 0;
+	}
+
+	// CPU memory accesses, each one takes an M-cycle of the current instruction
+	pub fn cpu_read(&mut self, addr: u16) -> u8 {
+		self.catch_up_apu(addr);
+		let data = self.read(addr);
+		self.access_cycles += 4;
+
+		data
+	}
+
+	pub fn cpu_write(&mut self, addr: u16, data: u8) {
+		self.catch_up_apu(addr);
+		self.write(addr, data);
+		self.access_cycles += 4;
+	}
+
+	// The APU is stepped after each instruction, run it up to this access first (DMG wave RAM timing)
+	fn catch_up_apu(&self, addr: u16) {
+		if !(0xFF10..=0xFF3F).contains(&addr) {
+			return;
+		}
+
+		if let (Some(apu), Some(timer)) = (&self.apu, &self.timer) {
+			let div_counter = timer.lock().unwrap().counter;
+			apu
+				.lock()
+				.unwrap()
+				.catch_up(self.access_cycles, div_counter);
 		}
 	}
 
 	pub fn read(&self, addr: u16) -> u8 {
+		if self.dma.is_blocking() && addr < 0xFF00 {
+			// During OAM DMA the CPU only sees I/O and HRAM
+			debug!("Read at 0x{:04X} blocked by OAM DMA", addr);
+			return 0xFF;
+		}
+
+		self.read_memory(addr)
+	}
+
+	fn read_memory(&self, addr: u16) -> u8 {
 		if addr < 0x100 && self.disable_boot == false {
 			// Boot
 			return self.memory[addr as usize];
@@ -70,6 +142,10 @@ impl BUS {
 		if addr < 0xC000 {
 			// External RAM
 			debug!("Accessing External RAM at 0x{:04X}", addr);
+			if let Some(cart) = &self.cartridge {
+				let cart = cart.lock().unwrap();
+				return cart.read(addr);
+			}
 			return self.memory[addr as usize];
 		}
 
@@ -88,6 +164,10 @@ impl BUS {
 		if addr < 0xFEA0 {
 			// OAM
 			debug!("Accessing OAM at 0x{:04X}", addr);
+			if let Some(ppu) = &self.ppu {
+				let ppu = ppu.lock().unwrap();
+				return ppu.read(addr);
+			}
 			return self.memory[addr as usize];
 		}
 
@@ -99,10 +179,42 @@ impl BUS {
 
 		if addr < 0xFF80 {
 			// I/O Registers
+			if addr == 0xFF00 {
+				// Joypad
+				if let Some(joypad) = &self.joypad {
+					let joypad = joypad.lock().unwrap();
+					return joypad.read();
+				}
+			}
+
+			if (0xFF04..=0xFF07).contains(&addr) {
+				// Timer
+				if let Some(timer) = &self.timer {
+					let timer = timer.lock().unwrap();
+					return timer.read(addr);
+				}
+			}
+
+			if addr == 0xFF0F {
+				// Interrupt Flag register (IF)
+				if let Some(interrupt) = &self.interrupt {
+					let interrupt = interrupt.lock().unwrap();
+					return interrupt.read(addr);
+				}
+			}
+
+			if (0xFF10..=0xFF3F).contains(&addr) {
+				// Audio
+				if let Some(apu) = &self.apu {
+					let apu = apu.lock().unwrap();
+					return apu.read(addr);
+				}
+			}
+
 			if addr >= 0xFF40 && addr <= 0xFF4B {
 				if addr == 0xFF46 {
 					debug!("Accessing OAM DMA at 0x{:04X}", addr);
-					return self.memory[addr as usize];
+					return self.dma.register;
 				}
 
 				if let Some(ppu) = &self.ppu {
@@ -123,6 +235,10 @@ impl BUS {
 		if addr == 0xFFFF {
 			// Interrupt Enable register (IE)
 			debug!("Accessing Interrupt Enable register at 0x{:04X}", addr);
+			if let Some(interrupt) = &self.interrupt {
+				let interrupt = interrupt.lock().unwrap();
+				return interrupt.read(addr);
+			}
 			return self.memory[addr as usize];
 		}
 
@@ -130,8 +246,13 @@ impl BUS {
 	}
 
 	pub fn write(&mut self, addr: u16, data: u8) {
-		if addr < 0x100 {
-			// BOOT
+		if self.dma.is_blocking() && addr < 0xFF00 {
+			debug!("Write at 0x{:04X} blocked by OAM DMA", addr);
+			return;
+		}
+
+		if addr < 0x100 && self.cartridge.is_none() {
+			// Flat memory (SM83 tests), with a cartridge the boot ROM area is read only
 			self.memory[addr as usize] = data;
 			return;
 		}
@@ -158,6 +279,11 @@ impl BUS {
 		if addr < 0xC000 {
 			// External RAM
 			debug!("Writing to External RAM at 0x{:04X}", addr);
+			if let Some(cart) = &self.cartridge {
+				let mut cart = cart.lock().unwrap();
+				cart.write(addr, data);
+				return;
+			}
 			self.memory[addr as usize] = data;
 			return;
 		}
@@ -179,6 +305,11 @@ impl BUS {
 		if addr < 0xFEA0 {
 			// OAM
 			debug!("Writing to OAM at 0x{:04X}", addr);
+			if let Some(ppu) = &self.ppu {
+				let mut ppu = ppu.lock().unwrap();
+				ppu.write(addr, data);
+				return;
+			}
 			self.memory[addr as usize] = data;
 			return;
 		}
@@ -199,10 +330,46 @@ impl BUS {
 				return;
 			}
 
+			if addr == 0xFF00 {
+				// Joypad
+				if let Some(joypad) = &self.joypad {
+					let mut joypad = joypad.lock().unwrap();
+					joypad.write(data);
+					return;
+				}
+			}
+
+			if (0xFF04..=0xFF07).contains(&addr) {
+				// Timer
+				if let Some(timer) = &self.timer {
+					let mut timer = timer.lock().unwrap();
+					timer.write(addr, data);
+					return;
+				}
+			}
+
+			if addr == 0xFF0F {
+				// Interrupt Flag register (IF)
+				if let Some(interrupt) = &self.interrupt {
+					let mut interrupt = interrupt.lock().unwrap();
+					interrupt.write(addr, data);
+					return;
+				}
+			}
+
+			if (0xFF10..=0xFF3F).contains(&addr) {
+				// Audio
+				if let Some(apu) = &self.apu {
+					let mut apu = apu.lock().unwrap();
+					apu.write(addr, data);
+					return;
+				}
+			}
+
 			if addr >= 0xFF40 && addr <= 0xFF4B {
 				if addr == 0xFF46 {
 					debug!("Writing to OAM DMA at 0x{:04X}", addr);
-					self.memory[addr as usize] = data;
+					self.dma.start(data);
 					return;
 				}
 
@@ -228,6 +395,11 @@ impl BUS {
 		if addr == 0xFFFF {
 			// Interrupt Enable register (IE)
 			debug!("Writing to Interrupt Enable register at 0x{:04X}", addr);
+			if let Some(interrupt) = &self.interrupt {
+				let mut interrupt = interrupt.lock().unwrap();
+				interrupt.write(addr, data);
+				return;
+			}
 			self.memory[addr as usize] = data;
 			return;
 		}
@@ -241,6 +413,38 @@ impl BUS {
 		self.ppu = Some(ppu);
 	}
 
+	pub fn interrupt_connect(&mut self, interrupt: Arc<Mutex<Interrupt>>) {
+		self.interrupt = Some(interrupt);
+	}
+
+	pub fn timer_connect(&mut self, timer: Arc<Mutex<Timer>>) {
+		self.timer = Some(timer);
+	}
+
+	pub fn apu_connect(&mut self, apu: Arc<Mutex<APU>>) {
+		self.apu = Some(apu);
+	}
+
+	pub fn joypad_connect(&mut self, joypad: Arc<Mutex<Joypad>>) {
+		self.joypad = Some(joypad);
+	}
+
+	// Advance the OAM DMA transfer, copying one byte per M-cycle into the PPU OAM
+	pub fn step_dma(&mut self, cycles: u32) {
+		for _ in 0..cycles {
+			if let Some((source, offset)) = self.dma.step() {
+				let data = self.read_memory(source);
+
+				if let Some(ppu) = &self.ppu {
+					let mut ppu = ppu.lock().unwrap();
+					ppu.oam[offset as usize] = data;
+				} else {
+					self.memory[0xFE00 + offset as usize] = data;
+				}
+			}
+		}
+	}
+
 	pub fn _dump_hram(&self) {
 		println!("HRAM Dump (0xFF80 - 0xFFFE):");
 		for i in 0xFF80..=0xFFFE {
@@ -249,3 +453,36 @@ impl BUS {
 		println!();
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::core::cartridge::write_test_rom;
+
+	#[test]
+	fn low_page_writes_reach_the_mapper() {
+		let rom_path = write_test_rom("bus_ram_enable", 0x03, 0x02); // MBC1+RAM+BATTERY, 8 KiB
+
+		let cartridge = Arc::new(Mutex::new(Cartridge::new()));
+		cartridge.lock().unwrap().battery_saves = false;
+		cartridge
+			.lock()
+			.unwrap()
+			.load_rom(rom_path.to_string_lossy().to_string())
+			.unwrap();
+
+		let mut bus = BUS::new();
+		bus.cartridge_connect(Arc::clone(&cartridge));
+		bus.memory[0x0000] = 0x31; // boot ROM byte
+
+		bus.write(0xA000, 0x42);
+		assert_eq!(bus.read(0xA000), 0xFF, "RAM starts disabled");
+
+		bus.write(0x0000, 0x0A);
+		bus.write(0xA000, 0x42);
+		assert_eq!(bus.read(0xA000), 0x42);
+		assert_eq!(bus.memory[0x0000], 0x31, "boot ROM area is read only");
+
+		let _ = std::fs::remove_file(rom_path);
+	}
+}
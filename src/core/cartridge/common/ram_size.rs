@@ -9,3 +9,14 @@ pub fn get_ram_size(code: u8) -> &'static str {
 		_ => "Unknown",
 	}
 }
+
+// External RAM size in bytes
+pub fn get_ram_size_bytes(code: u8) -> usize {
+	match code {
+		0x02 => 0x2000,
+		0x03 => 0x8000,
+		0x04 => 0x20000,
+		0x05 => 0x10000,
+		_ => 0,
+	}
+}
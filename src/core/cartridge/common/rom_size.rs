@@ -15,3 +15,14 @@ pub fn get_rom_size(code: u8) -> &'static str {
 		_ => "Unknown",
 	}
 }
+
+// ROM size in bytes
+pub fn get_rom_size_bytes(code: u8) -> Option<usize> {
+	match code {
+		0x00..=0x08 => Some(0x8000 << code),
+		0x52 => Some(72 * 0x4000),
+		0x53 => Some(80 * 0x4000),
+		0x54 => Some(96 * 0x4000),
+		_ => None,
+	}
+}
@@ -0,0 +1,56 @@
+use std::fmt;
+use std::io;
+
+// Header ends at 0x14F, anything shorter can't be a valid ROM
+pub const HEADER_END: usize = 0x150;
+
+#[derive(Debug)]
+pub enum CartridgeError {
+	Io(io::Error),
+	TooSmall(usize), // file size
+}
+
+impl fmt::Display for CartridgeError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			CartridgeError::Io(e) => write!(f, "Failed to read ROM: {}", e),
+			CartridgeError::TooSmall(size) => write!(
+				f,
+				"ROM is too small ({} bytes), the header needs at least {} bytes",
+				size, HEADER_END
+			),
+		}
+	}
+}
+
+impl std::error::Error for CartridgeError {}
+
+impl From<io::Error> for CartridgeError {
+	fn from(e: io::Error) -> Self {
+		CartridgeError::Io(e)
+	}
+}
+
+// Header problems that don't stop the ROM from running
+#[derive(Debug, Clone, PartialEq)]
+pub enum CartridgeWarning {
+	BadLogo,
+	HeaderChecksum { expected: u8, computed: u8 },
+	RomSize { expected: usize, actual: usize },
+}
+
+impl fmt::Display for CartridgeWarning {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			CartridgeWarning::BadLogo => write!(f, "Nintendo logo does not match"),
+			CartridgeWarning::HeaderChecksum { expected, computed } => write!(
+				f,
+				"Header checksum mismatch (header: {:02X}, computed: {:02X})",
+				expected, computed
+			),
+			CartridgeWarning::RomSize { expected, actual } => {
+				write!(f, "ROM size mismatch (header: {} bytes, file: {} bytes)", expected, actual)
+			}
+		}
+	}
+}
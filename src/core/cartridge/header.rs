@@ -0,0 +1,335 @@
+use std::fmt;
+
+use super::common::cartridge_destination::get_destination;
+use super::common::cartridge_type::get_cartridge_type;
+use super::common::licensee_codes::{get_old_publisher, get_publisher};
+use super::common::ram_size::{get_ram_size, get_ram_size_bytes};
+use super::common::rom_size::{get_rom_size, get_rom_size_bytes};
+use super::error::{CartridgeError, CartridgeWarning, HEADER_END};
+
+/*
++-----------+-------------------------------------------------------------+
+| Addr      | Field                                                       |
++-----------+-------------------------------------------------------------+
+| 0104-0133 | Nintendo logo                                               |
+| 0134-0143 | Title (CGB carts: 0134-013E title, 013F-0142 manufacturer)  |
+| 0143      | CGB flag                                                    |
+| 0144-0145 | New licensee code                                           |
+| 0146      | SGB flag                                                    |
+| 0147      | Cartridge type                                              |
+| 0148      | ROM size                                                    |
+| 0149      | RAM size                                                    |
+| 014A      | Destination code                                            |
+| 014B      | Old licensee code (0x33 = use the new licensee code)        |
+| 014C      | Mask ROM version                                            |
+| 014D      | Header checksum                                             |
+| 014E-014F | Global checksum (big endian)                                |
++-----------+-------------------------------------------------------------+
+*/
+
+// Logo bitmap at 0x104-0x133, checked by the boot ROM
+pub const NINTENDO_LOGO: [u8; 0x30] = [
+	0xCE, 0xED, 0x66, 0x66, 0xCC, 0x0D, 0x00, 0x0B, 0x03, 0x73, 0x00, 0x83, 0x00, 0x0C, 0x00, 0x0D,
+	0x00, 0x08, 0x11, 0x1F, 0x88, 0x89, 0x00, 0x0E, 0xDC, 0xCC, 0x6E, 0xE6, 0xDD, 0xDD, 0xD9, 0x99,
+	0xBB, 0xBB, 0x67, 0x63, 0x6E, 0x0E, 0xEC, 0xCC, 0xDD, 0xDC, 0x99, 0x9F, 0xBB, 0xB9, 0x33, 0x3E,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CgbFlag {
+	DmgOnly,
+	CgbEnhanced, // 0x80, also runs on DMG
+	CgbOnly,     // 0xC0
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Licensee {
+	Old(u8),
+	New(String), // two ASCII characters
+}
+
+impl Licensee {
+	pub fn code(&self) -> String {
+		match self {
+			Licensee::Old(code) => format!("{:02X}", code),
+			Licensee::New(code) => code.clone(),
+		}
+	}
+
+	pub fn publisher(&self) -> &'static str {
+		match self {
+			Licensee::Old(code) => get_old_publisher(*code as u16),
+			Licensee::New(code) => get_publisher(code.clone()),
+		}
+	}
+}
+
+impl CgbFlag {
+	pub fn description(&self) -> &'static str {
+		match self {
+			CgbFlag::DmgOnly => "DMG only",
+			CgbFlag::CgbEnhanced => "CGB enhanced (DMG compatible)",
+			CgbFlag::CgbOnly => "CGB only",
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Destination {
+	Japan,
+	Overseas,
+	Unknown(u8),
+}
+
+// Memory bank controller named by the cartridge type
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mbc {
+	RomOnly,
+	MBC1,
+	MBC2,
+	MBC3,
+	MBC5,
+	MBC6,
+	MBC7,
+	MMM01,
+	PocketCamera,
+	TAMA5,
+	HuC1,
+	HuC3,
+	Unknown,
+}
+
+// Cartridge type (0x147) split into the controller and the extra hardware on the board
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CartridgeType {
+	pub code: u8,
+	pub mbc: Mbc,
+	pub ram: bool,
+	pub battery: bool,
+	pub rtc: bool,
+	pub rumble: bool,
+}
+
+impl CartridgeType {
+	pub fn from_code(code: u8) -> Self {
+		let mbc = match code {
+			0x00 | 0x08 | 0x09 => Mbc::RomOnly,
+			0x01..=0x03 => Mbc::MBC1,
+			0x05 | 0x06 => Mbc::MBC2,
+			0x0B..=0x0D => Mbc::MMM01,
+			0x0F..=0x13 => Mbc::MBC3,
+			0x19..=0x1E => Mbc::MBC5,
+			0x20 => Mbc::MBC6,
+			0x22 => Mbc::MBC7,
+			0xFC => Mbc::PocketCamera,
+			0xFD => Mbc::TAMA5,
+			0xFE => Mbc::HuC3,
+			0xFF => Mbc::HuC1,
+			_ => Mbc::Unknown,
+		};
+
+		Self {
+			code,
+			mbc,
+			ram: matches!(
+				code,
+				0x02..=0x03 | 0x08..=0x09 | 0x0C..=0x0D | 0x10 | 0x12..=0x13 | 0x1A..=0x1B | 0x1D..=0x1E | 0x22 | 0xFF
+			),
+			battery: matches!(
+				code,
+				0x03 | 0x06 | 0x09 | 0x0D | 0x0F | 0x10 | 0x13 | 0x1B | 0x1E | 0x22 | 0xFF
+			),
+			rtc: matches!(code, 0x0F | 0x10),
+			rumble: matches!(code, 0x1C..=0x1E | 0x22),
+		}
+	}
+
+	pub fn name(&self) -> &'static str {
+		get_cartridge_type(self.code)
+	}
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CartridgeHeader {
+	pub title: String,
+	pub manufacturer_code: Option<String>,
+	pub cgb_flag: CgbFlag,
+	pub licensee: Licensee,
+	pub sgb: bool,
+	pub cartridge_type: CartridgeType,
+	pub rom_size_code: u8,
+	pub rom_size: Option<usize>, // bytes, None for unknown codes
+	pub ram_size_code: u8,
+	pub ram_size: usize, // bytes
+	pub destination: Destination,
+	pub version: u8,
+	pub header_checksum: u8,
+	pub computed_header_checksum: u8,
+	pub global_checksum: u16,
+	pub computed_global_checksum: u16,
+	pub logo_valid: bool,
+}
+
+impl CartridgeHeader {
+	pub fn parse(rom: &[u8]) -> Result<Self, CartridgeError> {
+		if rom.len() < HEADER_END {
+			return Err(CartridgeError::TooSmall(rom.len()));
+		}
+
+		let cgb_flag = match rom[0x143] {
+			0xC0 => CgbFlag::CgbOnly,
+			0x80 => CgbFlag::CgbEnhanced,
+			_ => CgbFlag::DmgOnly,
+		};
+
+		// CGB carts shortened the title to make room for the manufacturer code and the CGB flag
+		let manufacturer = &rom[0x13F..=0x142];
+		let manufacturer_code =
+			if cgb_flag != CgbFlag::DmgOnly && manufacturer.iter().all(|c| c.is_ascii_uppercase()) {
+				Some(String::from_utf8_lossy(manufacturer).to_string())
+			} else {
+				None
+			};
+
+		let title_end = match (cgb_flag, &manufacturer_code) {
+			(_, Some(_)) => 0x13E,
+			(CgbFlag::DmgOnly, None) => 0x143,
+			(_, None) => 0x142,
+		};
+		let title = String::from_utf8_lossy(&rom[0x134..=title_end])
+			.trim_end_matches(char::from(0))
+			.to_string();
+
+		let licensee = match rom[0x14B] {
+			0x33 => Licensee::New(String::from_utf8_lossy(&rom[0x144..=0x145]).to_string()),
+			code => Licensee::Old(code),
+		};
+
+		let destination = match rom[0x14A] {
+			0x00 => Destination::Japan,
+			0x01 => Destination::Overseas,
+			code => Destination::Unknown(code),
+		};
+
+		Ok(Self {
+			title,
+			manufacturer_code,
+			cgb_flag,
+			licensee,
+			sgb: rom[0x146] == 0x03,
+			cartridge_type: CartridgeType::from_code(rom[0x147]),
+			rom_size_code: rom[0x148],
+			rom_size: get_rom_size_bytes(rom[0x148]),
+			ram_size_code: rom[0x149],
+			ram_size: get_ram_size_bytes(rom[0x149]),
+			destination,
+			version: rom[0x14C],
+			header_checksum: rom[0x14D],
+			computed_header_checksum: header_checksum(rom),
+			global_checksum: u16::from_be_bytes([rom[0x14E], rom[0x14F]]),
+			computed_global_checksum: global_checksum(rom),
+			logo_valid: rom[0x104..0x134] == NINTENDO_LOGO,
+		})
+	}
+
+	pub fn cartridge_type_name(&self) -> &'static str {
+		self.cartridge_type.name()
+	}
+
+	pub fn rom_size_name(&self) -> &'static str {
+		get_rom_size(self.rom_size_code)
+	}
+
+	pub fn ram_size_name(&self) -> &'static str {
+		get_ram_size(self.ram_size_code)
+	}
+
+	pub fn destination_name(&self) -> &'static str {
+		get_destination(self.destination_code())
+	}
+
+	pub fn has_battery(&self) -> bool {
+		self.cartridge_type.battery
+	}
+
+	pub fn header_checksum_valid(&self) -> bool {
+		self.header_checksum == self.computed_header_checksum
+	}
+
+	// Not checked by the hardware, some official ROMs get it wrong
+	pub fn global_checksum_valid(&self) -> bool {
+		self.global_checksum == self.computed_global_checksum
+	}
+
+	// Header checks the boot ROM (or real hardware) would fail on, we run the game anyway
+	pub fn validate(&self, file_size: usize) -> Vec<CartridgeWarning> {
+		let mut warnings = Vec::new();
+
+		if !self.logo_valid {
+			warnings.push(CartridgeWarning::BadLogo);
+		}
+
+		if !self.header_checksum_valid() {
+			warnings.push(CartridgeWarning::HeaderChecksum {
+				expected: self.header_checksum,
+				computed: self.computed_header_checksum,
+			});
+		}
+
+		if let Some(expected) = self.rom_size {
+			if expected != file_size {
+				warnings.push(CartridgeWarning::RomSize {
+					expected,
+					actual: file_size,
+				});
+			}
+		}
+
+		warnings
+	}
+
+	pub fn destination_code(&self) -> u8 {
+		match self.destination {
+			Destination::Japan => 0x00,
+			Destination::Overseas => 0x01,
+			Destination::Unknown(code) => code,
+		}
+	}
+}
+
+impl fmt::Display for CartridgeHeader {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		writeln!(f, "Game Title: {}", self.title)?;
+		writeln!(f, "Game Version: {}.0", self.version)?;
+		writeln!(f, "Licensee: {}", self.licensee.publisher())?;
+		writeln!(f, "Cartridge Type: {}", self.cartridge_type_name())?;
+		writeln!(f, "ROM Size: {}", self.rom_size_name())?;
+		writeln!(f, "RAM Size: {}", self.ram_size_name())?;
+		writeln!(f, "Cartridge Destination: {}", self.destination_name())?;
+		write!(
+			f,
+			"Checksum: {:02X} ({})",
+			self.computed_header_checksum,
+			if self.header_checksum_valid() {
+				"PASSED"
+			} else {
+				"FAILED"
+			}
+		)
+	}
+}
+
+// Checksum of 0x134-0x14C, verified by the boot ROM
+fn header_checksum(rom: &[u8]) -> u8 {
+	rom[0x134..=0x14C]
+		.iter()
+		.fold(0u8, |checksum, byte| checksum.wrapping_sub(*byte).wrapping_sub(1))
+}
+
+// Sum of every ROM byte except the checksum itself
+fn global_checksum(rom: &[u8]) -> u16 {
+	rom
+		.iter()
+		.enumerate()
+		.filter(|(addr, _)| *addr != 0x14E && *addr != 0x14F)
+		.fold(0u16, |checksum, (_, byte)| checksum.wrapping_add(*byte as u16))
+}
@@ -0,0 +1,121 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::{ram_offset, read_rom_bank, Mapper};
+use crate::common::serde_base64;
+
+/*
++-----------+-------------------------------------------------------------+
+| Addr      | Register                                                    |
++-----------+-------------------------------------------------------------+
+| 0000-1FFF | RAM enable (0x0A in the lower nibble enables)               |
+| 2000-3FFF | ROM bank, lower 5 bits (0 is treated as 1)                  |
+| 4000-5FFF | RAM bank or upper 2 bits of the ROM bank                    |
+| 6000-7FFF | Banking mode (0 = simple, 1 = advanced)                     |
++-----------+-------------------------------------------------------------+
+
+In advanced mode the 2-bit register also applies to 0000-3FFF and to the RAM bank.
+*/
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MBC1 {
+	#[serde(with = "serde_base64")]
+	ram: Vec<u8>,
+	ram_enabled: bool,
+	rom_bank: u8,   // 5-bit register
+	upper_bank: u8, // 2-bit register
+	advanced_mode: bool,
+}
+
+impl MBC1 {
+	pub fn new(ram_size: usize) -> Self {
+		Self {
+			ram: vec![0; ram_size],
+			ram_enabled: false,
+			rom_bank: 0x01,
+			upper_bank: 0x00,
+			advanced_mode: false,
+		}
+	}
+
+	fn ram_bank(&self) -> usize {
+		if self.advanced_mode {
+			self.upper_bank as usize
+		} else {
+			0
+		}
+	}
+}
+
+impl Mapper for MBC1 {
+	fn read_rom(&self, rom: &[u8], addr: u16) -> u8 {
+		if addr < 0x4000 {
+			let bank = if self.advanced_mode {
+				(self.upper_bank as usize) << 5
+			} else {
+				0
+			};
+			return read_rom_bank(rom, bank, addr);
+		}
+
+		let bank = (self.upper_bank as usize) << 5 | self.rom_bank as usize;
+		read_rom_bank(rom, bank, addr)
+	}
+
+	fn write_rom(&mut self, addr: u16, data: u8) {
+		match addr {
+			0x0000..=0x1FFF => self.ram_enabled = data & 0x0F == 0x0A,
+			0x2000..=0x3FFF => {
+				// The zero check only looks at the 5-bit register, so banks 0x20/0x40/0x60 map to 0x21/0x41/0x61
+				self.rom_bank = data & 0x1F;
+				if self.rom_bank == 0 {
+					self.rom_bank = 1;
+				}
+			}
+			0x4000..=0x5FFF => self.upper_bank = data & 0x03,
+			_ => self.advanced_mode = data & 0x01 != 0,
+		}
+	}
+
+	fn read_ram(&self, addr: u16) -> u8 {
+		if !self.ram_enabled {
+			return 0xFF;
+		}
+
+		match ram_offset(&self.ram, self.ram_bank(), addr) {
+			Some(offset) => self.ram[offset],
+			None => 0xFF,
+		}
+	}
+
+	fn write_ram(&mut self, addr: u16, data: u8) -> bool {
+		if !self.ram_enabled {
+			return false;
+		}
+
+		match ram_offset(&self.ram, self.ram_bank(), addr) {
+			Some(offset) => {
+				self.ram[offset] = data;
+				true
+			}
+			None => false,
+		}
+	}
+
+	fn ram(&self) -> &[u8] {
+		&self.ram
+	}
+
+	fn ram_mut(&mut self) -> &mut [u8] {
+		&mut self.ram
+	}
+
+	fn save_state(&self) -> serde_json::Result<Value> {
+		serde_json::to_value(self)
+	}
+
+	fn load_state(&mut self, state: Value) -> serde_json::Result<()> {
+		*self = serde_json::from_value(state)?;
+		Ok(())
+	}
+}
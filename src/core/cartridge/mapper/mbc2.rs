@@ -0,0 +1,96 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::{read_rom_bank, Mapper};
+use crate::common::serde_base64;
+
+/*
++-----------+-------------------------------------------------------------+
+| Addr      | Register                                                    |
++-----------+-------------------------------------------------------------+
+| 0000-3FFF | Address bit 8 clear: RAM enable (0x0A in the lower nibble)  |
+|           | Address bit 8 set: ROM bank, 4 bits (0 is treated as 1)     |
++-----------+-------------------------------------------------------------+
+
+The built-in RAM is 512 x 4 bits, mirrored across A000-BFFF.
+*/
+
+const RAM_SIZE: usize = 0x200;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MBC2 {
+	#[serde(with = "serde_base64")]
+	ram: Vec<u8>,
+	ram_enabled: bool,
+	rom_bank: u8,
+}
+
+impl MBC2 {
+	pub fn new() -> Self {
+		Self {
+			ram: vec![0; RAM_SIZE],
+			ram_enabled: false,
+			rom_bank: 0x01,
+		}
+	}
+}
+
+impl Mapper for MBC2 {
+	fn read_rom(&self, rom: &[u8], addr: u16) -> u8 {
+		if addr < 0x4000 {
+			return read_rom_bank(rom, 0, addr);
+		}
+
+		read_rom_bank(rom, self.rom_bank as usize, addr)
+	}
+
+	fn write_rom(&mut self, addr: u16, data: u8) {
+		if addr >= 0x4000 {
+			return;
+		}
+
+		if addr & 0x0100 == 0 {
+			self.ram_enabled = data & 0x0F == 0x0A;
+		} else {
+			self.rom_bank = data & 0x0F;
+			if self.rom_bank == 0 {
+				self.rom_bank = 1;
+			}
+		}
+	}
+
+	fn read_ram(&self, addr: u16) -> u8 {
+		if !self.ram_enabled {
+			return 0xFF;
+		}
+
+		// Only the lower nibble exists, the upper one reads as 1s
+		self.ram[addr as usize & (RAM_SIZE - 1)] | 0xF0
+	}
+
+	fn write_ram(&mut self, addr: u16, data: u8) -> bool {
+		if !self.ram_enabled {
+			return false;
+		}
+
+		self.ram[addr as usize & (RAM_SIZE - 1)] = data & 0x0F;
+		true
+	}
+
+	fn ram(&self) -> &[u8] {
+		&self.ram
+	}
+
+	fn ram_mut(&mut self) -> &mut [u8] {
+		&mut self.ram
+	}
+
+	fn save_state(&self) -> serde_json::Result<Value> {
+		serde_json::to_value(self)
+	}
+
+	fn load_state(&mut self, state: Value) -> serde_json::Result<()> {
+		*self = serde_json::from_value(state)?;
+		Ok(())
+	}
+}
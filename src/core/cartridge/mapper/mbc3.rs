@@ -0,0 +1,141 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::rtc::Rtc;
+use super::{ram_offset, read_rom_bank, Mapper};
+use crate::common::serde_base64;
+
+/*
++-----------+-------------------------------------------------------------+
+| Addr      | Register                                                    |
++-----------+-------------------------------------------------------------+
+| 0000-1FFF | RAM and RTC enable (0x0A in the lower nibble enables)       |
+| 2000-3FFF | ROM bank, 7 bits (0 is treated as 1)                        |
+| 4000-5FFF | 00-03 selects a RAM bank, 08-0C selects an RTC register     |
+| 6000-7FFF | Writing 00 then 01 latches the clock into 08-0C             |
++-----------+-------------------------------------------------------------+
+*/
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MBC3 {
+	#[serde(with = "serde_base64")]
+	ram: Vec<u8>,
+	ram_enabled: bool,
+	rom_bank: u8,
+	select: u8, // RAM bank or RTC register mapped at A000-BFFF
+	latch_armed: bool,
+	rtc: Option<Rtc>,
+}
+
+impl MBC3 {
+	pub fn new(ram_size: usize, has_rtc: bool) -> Self {
+		Self {
+			ram: vec![0; ram_size],
+			ram_enabled: false,
+			rom_bank: 0x01,
+			select: 0x00,
+			latch_armed: false,
+			rtc: if has_rtc { Some(Rtc::new()) } else { None },
+		}
+	}
+}
+
+impl Mapper for MBC3 {
+	fn read_rom(&self, rom: &[u8], addr: u16) -> u8 {
+		if addr < 0x4000 {
+			return read_rom_bank(rom, 0, addr);
+		}
+
+		read_rom_bank(rom, self.rom_bank as usize, addr)
+	}
+
+	fn write_rom(&mut self, addr: u16, data: u8) {
+		match addr {
+			0x0000..=0x1FFF => self.ram_enabled = data & 0x0F == 0x0A,
+			0x2000..=0x3FFF => {
+				self.rom_bank = data & 0x7F;
+				if self.rom_bank == 0 {
+					self.rom_bank = 1;
+				}
+			}
+			0x4000..=0x5FFF => self.select = data,
+			_ => {
+				if self.latch_armed && data == 0x01 {
+					if let Some(rtc) = &mut self.rtc {
+						rtc.latch();
+					}
+				}
+				self.latch_armed = data == 0x00;
+			}
+		}
+	}
+
+	fn read_ram(&self, addr: u16) -> u8 {
+		if !self.ram_enabled {
+			return 0xFF;
+		}
+
+		match self.select {
+			0x00..=0x03 => match ram_offset(&self.ram, self.select as usize, addr) {
+				Some(offset) => self.ram[offset],
+				None => 0xFF,
+			},
+			0x08..=0x0C => match &self.rtc {
+				Some(rtc) => rtc.read(self.select),
+				None => 0xFF,
+			},
+			_ => 0xFF,
+		}
+	}
+
+	fn write_ram(&mut self, addr: u16, data: u8) -> bool {
+		if !self.ram_enabled {
+			return false;
+		}
+
+		match self.select {
+			0x00..=0x03 => match ram_offset(&self.ram, self.select as usize, addr) {
+				Some(offset) => {
+					self.ram[offset] = data;
+					true
+				}
+				None => false,
+			},
+			0x08..=0x0C => match &mut self.rtc {
+				Some(rtc) => {
+					rtc.write(self.select, data);
+					true
+				}
+				None => false,
+			},
+			_ => false,
+		}
+	}
+
+	fn ram(&self) -> &[u8] {
+		&self.ram
+	}
+
+	fn ram_mut(&mut self) -> &mut [u8] {
+		&mut self.ram
+	}
+
+	fn step(&mut self, cycles: u32) {
+		if let Some(rtc) = &mut self.rtc {
+			rtc.step(cycles);
+		}
+	}
+
+	fn rtc(&mut self) -> Option<&mut Rtc> {
+		self.rtc.as_mut()
+	}
+
+	fn save_state(&self) -> serde_json::Result<Value> {
+		serde_json::to_value(self)
+	}
+
+	fn load_state(&mut self, state: Value) -> serde_json::Result<()> {
+		*self = serde_json::from_value(state)?;
+		Ok(())
+	}
+}
@@ -0,0 +1,114 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::{ram_offset, read_rom_bank, Mapper};
+use crate::common::serde_base64;
+
+/*
++-----------+-------------------------------------------------------------+
+| Addr      | Register                                                    |
++-----------+-------------------------------------------------------------+
+| 0000-1FFF | RAM enable (0x0A enables)                                   |
+| 2000-2FFF | Lower 8 bits of the ROM bank (0 is a valid bank)            |
+| 3000-3FFF | Bit 8 of the ROM bank                                       |
+| 4000-5FFF | RAM bank 00-0F, on rumble carts bit 3 drives the motor      |
++-----------+-------------------------------------------------------------+
+*/
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MBC5 {
+	#[serde(with = "serde_base64")]
+	ram: Vec<u8>,
+	ram_enabled: bool,
+	rom_bank: u16, // 9-bit register
+	ram_bank: u8,
+	has_rumble: bool,
+	rumble: bool,
+}
+
+impl MBC5 {
+	pub fn new(ram_size: usize, has_rumble: bool) -> Self {
+		Self {
+			ram: vec![0; ram_size],
+			ram_enabled: false,
+			rom_bank: 0x001,
+			ram_bank: 0x00,
+			has_rumble,
+			rumble: false,
+		}
+	}
+}
+
+impl Mapper for MBC5 {
+	fn read_rom(&self, rom: &[u8], addr: u16) -> u8 {
+		if addr < 0x4000 {
+			return read_rom_bank(rom, 0, addr);
+		}
+
+		read_rom_bank(rom, self.rom_bank as usize, addr)
+	}
+
+	fn write_rom(&mut self, addr: u16, data: u8) {
+		match addr {
+			0x0000..=0x1FFF => self.ram_enabled = data == 0x0A,
+			0x2000..=0x2FFF => self.rom_bank = (self.rom_bank & 0x100) | data as u16,
+			0x3000..=0x3FFF => self.rom_bank = (self.rom_bank & 0xFF) | ((data as u16 & 0x01) << 8),
+			0x4000..=0x5FFF => {
+				if self.has_rumble {
+					// The motor takes over bit 3, leaving 8 RAM banks
+					self.rumble = data & 0x08 != 0;
+					self.ram_bank = data & 0x07;
+				} else {
+					self.ram_bank = data & 0x0F;
+				}
+			}
+			_ => (),
+		}
+	}
+
+	fn read_ram(&self, addr: u16) -> u8 {
+		if !self.ram_enabled {
+			return 0xFF;
+		}
+
+		match ram_offset(&self.ram, self.ram_bank as usize, addr) {
+			Some(offset) => self.ram[offset],
+			None => 0xFF,
+		}
+	}
+
+	fn write_ram(&mut self, addr: u16, data: u8) -> bool {
+		if !self.ram_enabled {
+			return false;
+		}
+
+		match ram_offset(&self.ram, self.ram_bank as usize, addr) {
+			Some(offset) => {
+				self.ram[offset] = data;
+				true
+			}
+			None => false,
+		}
+	}
+
+	fn ram(&self) -> &[u8] {
+		&self.ram
+	}
+
+	fn ram_mut(&mut self) -> &mut [u8] {
+		&mut self.ram
+	}
+
+	fn rumble(&self) -> bool {
+		self.rumble
+	}
+
+	fn save_state(&self) -> serde_json::Result<Value> {
+		serde_json::to_value(self)
+	}
+
+	fn load_state(&mut self, state: Value) -> serde_json::Result<()> {
+		*self = serde_json::from_value(state)?;
+		Ok(())
+	}
+}
@@ -0,0 +1,85 @@
+#![allow(dead_code)]
+use serde_json::Value;
+use std::fmt::Debug;
+
+use super::header::{CartridgeType, Mbc};
+
+pub mod mbc1;
+pub mod mbc2;
+pub mod mbc3;
+pub mod mbc5;
+pub mod rom_only;
+pub mod rtc;
+
+use mbc1::MBC1;
+use mbc2::MBC2;
+use mbc3::MBC3;
+use mbc5::MBC5;
+use rom_only::RomOnly;
+use rtc::Rtc;
+
+pub const ROM_BANK_SIZE: usize = 0x4000; // 16 KiB
+pub const RAM_BANK_SIZE: usize = 0x2000; // 8 KiB
+
+// Memory bank controller, maps the CPU view of 0000-7FFF and A000-BFFF onto the ROM and external RAM
+pub trait Mapper: Debug + Send {
+	fn read_rom(&self, rom: &[u8], addr: u16) -> u8;
+	fn write_rom(&mut self, addr: u16, data: u8);
+	fn read_ram(&self, addr: u16) -> u8;
+	// Returns false when the write was ignored (RAM disabled or missing)
+	fn write_ram(&mut self, addr: u16, data: u8) -> bool;
+
+	// Battery backed RAM contents, persisted to the .sav file
+	fn ram(&self) -> &[u8] {
+		&[]
+	}
+
+	fn ram_mut(&mut self) -> &mut [u8] {
+		&mut []
+	}
+
+	// Advance mapper hardware driven by the system clock (T-cycles)
+	fn step(&mut self, _cycles: u32) {}
+
+	fn rtc(&mut self) -> Option<&mut Rtc> {
+		None
+	}
+
+	// Rumble motor state, only driven by MBC5 rumble carts
+	fn rumble(&self) -> bool {
+		false
+	}
+
+	// Registers, RAM and RTC for save states
+	fn save_state(&self) -> serde_json::Result<Value>;
+	fn load_state(&mut self, state: Value) -> serde_json::Result<()>;
+}
+
+// Pick the mapper from the cartridge type (0x147), unsupported controllers run as ROM only
+pub fn get_mapper(cartridge_type: &CartridgeType, ram_size: usize) -> Box<dyn Mapper> {
+	match cartridge_type.mbc {
+		Mbc::MBC1 => Box::new(MBC1::new(ram_size)),
+		Mbc::MBC2 => Box::new(MBC2::new()),
+		Mbc::MBC3 => Box::new(MBC3::new(ram_size, cartridge_type.rtc)),
+		Mbc::MBC5 => Box::new(MBC5::new(ram_size, cartridge_type.rumble)),
+		Mbc::RomOnly if cartridge_type.ram => Box::new(RomOnly::new(ram_size)),
+		_ => Box::new(RomOnly::new(0)),
+	}
+}
+
+// Byte at `addr` (0000-3FFF offset) of a ROM bank, the bank number wraps around the ROM size
+pub fn read_rom_bank(rom: &[u8], bank: usize, addr: u16) -> u8 {
+	let banks = rom.len().div_ceil(ROM_BANK_SIZE).max(1);
+	let offset = (bank % banks) * ROM_BANK_SIZE + (addr as usize & (ROM_BANK_SIZE - 1));
+
+	rom.get(offset).copied().unwrap_or(0xFF)
+}
+
+// Offset of `addr` (A000-BFFF) inside a RAM bank, wraps around the RAM size
+pub fn ram_offset(ram: &[u8], bank: usize, addr: u16) -> Option<usize> {
+	if ram.is_empty() {
+		return None;
+	}
+
+	Some((bank * RAM_BANK_SIZE + (addr as usize & (RAM_BANK_SIZE - 1))) % ram.len())
+}
@@ -0,0 +1,62 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::{ram_offset, read_rom_bank, Mapper};
+use crate::common::serde_base64;
+
+// 32 KiB ROM without banking, optionally with up to 8 KiB of RAM
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RomOnly {
+	#[serde(with = "serde_base64")]
+	ram: Vec<u8>,
+}
+
+impl RomOnly {
+	pub fn new(ram_size: usize) -> Self {
+		Self {
+			ram: vec![0; ram_size],
+		}
+	}
+}
+
+impl Mapper for RomOnly {
+	fn read_rom(&self, rom: &[u8], addr: u16) -> u8 {
+		read_rom_bank(rom, (addr >> 14) as usize, addr)
+	}
+
+	fn write_rom(&mut self, _addr: u16, _data: u8) {}
+
+	fn read_ram(&self, addr: u16) -> u8 {
+		match ram_offset(&self.ram, 0, addr) {
+			Some(offset) => self.ram[offset],
+			None => 0xFF,
+		}
+	}
+
+	fn write_ram(&mut self, addr: u16, data: u8) -> bool {
+		match ram_offset(&self.ram, 0, addr) {
+			Some(offset) => {
+				self.ram[offset] = data;
+				true
+			}
+			None => false,
+		}
+	}
+
+	fn ram(&self) -> &[u8] {
+		&self.ram
+	}
+
+	fn ram_mut(&mut self) -> &mut [u8] {
+		&mut self.ram
+	}
+
+	fn save_state(&self) -> serde_json::Result<Value> {
+		serde_json::to_value(self)
+	}
+
+	fn load_state(&mut self, state: Value) -> serde_json::Result<()> {
+		*self = serde_json::from_value(state)?;
+		Ok(())
+	}
+}
@@ -0,0 +1,206 @@
+#![allow(dead_code)]
+use serde::{Deserialize, Serialize};
+
+/*
+MBC3 real time clock registers
++------+------------------------------------------------------+
+| Reg  | Notes                                                |
++------+------------------------------------------------------+
+| 08   | Seconds (0-59)                                       |
+| 09   | Minutes (0-59)                                       |
+| 0A   | Hours (0-23)                                         |
+| 0B   | Lower 8 bits of the day counter                      |
+| 0C   | Bit 0: day counter bit 8, Bit 6: halt, Bit 7: carry  |
++------+------------------------------------------------------+
+*/
+
+const CYCLES_PER_SECOND: u32 = 4_194_304;
+
+/*
+Save file footer shared with BGB/VBA-M/SameBoy (48 bytes, little endian):
+	5 x u32 live registers (08-0C), 5 x u32 latched registers, u64 UNIX timestamp
+Older saves use a 44-byte variant with a 32-bit timestamp.
+*/
+pub const FOOTER_SIZE: usize = 48;
+pub const LEGACY_FOOTER_SIZE: usize = 44;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Rtc {
+	pub seconds: u8,
+	pub minutes: u8,
+	pub hours: u8,
+	pub days: u16, // 9-bit day counter
+	pub halt: bool,
+	pub carry: bool,
+	pub latched: [u8; 5], // copy of 08-0C taken by the latch sequence
+	sub_cycles: u32,      // T-cycles elapsed in the current second
+}
+
+impl Rtc {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	// Advance the clock by emulated T-cycles
+	pub fn step(&mut self, cycles: u32) {
+		if self.halt {
+			return;
+		}
+
+		self.sub_cycles += cycles;
+		while self.sub_cycles >= CYCLES_PER_SECOND {
+			self.sub_cycles -= CYCLES_PER_SECOND;
+			self.tick_second();
+		}
+	}
+
+	// Advance the clock by whole seconds (e.g. the host time elapsed since a save)
+	pub fn advance_seconds(&mut self, seconds: u64) {
+		if self.halt || seconds == 0 {
+			return;
+		}
+
+		let total = seconds
+			+ self.seconds as u64
+			+ self.minutes as u64 * 60
+			+ self.hours as u64 * 3600
+			+ self.days as u64 * 86400;
+		let days = total / 86400;
+
+		self.seconds = (total % 60) as u8;
+		self.minutes = (total / 60 % 60) as u8;
+		self.hours = (total / 3600 % 24) as u8;
+		self.days = (days % 512) as u16;
+		self.carry |= days > 0x1FF;
+	}
+
+	pub fn latch(&mut self) {
+		self.latched = [
+			self.seconds,
+			self.minutes,
+			self.hours,
+			self.days as u8,
+			self.day_high(),
+		];
+	}
+
+	pub fn read(&self, register: u8) -> u8 {
+		match register {
+			0x08..=0x0C => self.latched[(register - 0x08) as usize],
+			_ => 0xFF,
+		}
+	}
+
+	pub fn write(&mut self, register: u8, data: u8) {
+		match register {
+			0x08 => {
+				// Writing the seconds also resets the sub-second counter
+				self.seconds = data & 0x3F;
+				self.sub_cycles = 0;
+			}
+			0x09 => self.minutes = data & 0x3F,
+			0x0A => self.hours = data & 0x1F,
+			0x0B => self.days = (self.days & 0x100) | data as u16,
+			0x0C => {
+				self.days = (self.days & 0xFF) | ((data as u16 & 0x01) << 8);
+				self.halt = data & 0x40 != 0;
+				self.carry = data & 0x80 != 0;
+			}
+			_ => return,
+		}
+
+		// Keep the written value visible without another latch
+		self.latched[(register - 0x08) as usize] = match register {
+			0x0C => self.day_high(),
+			0x0B => self.days as u8,
+			0x0A => self.hours,
+			0x09 => self.minutes,
+			_ => self.seconds,
+		};
+	}
+
+	fn day_high(&self) -> u8 {
+		let mut value = (self.days >> 8) as u8 & 0x01;
+
+		if self.halt {
+			value |= 0x40;
+		}
+
+		if self.carry {
+			value |= 0x80;
+		}
+
+		value
+	}
+
+	// Out of range values count up to the register limit and wrap without carrying
+	fn tick_second(&mut self) {
+		self.seconds = (self.seconds + 1) & 0x3F;
+		if self.seconds != 60 {
+			return;
+		}
+		self.seconds = 0;
+
+		self.minutes = (self.minutes + 1) & 0x3F;
+		if self.minutes != 60 {
+			return;
+		}
+		self.minutes = 0;
+
+		self.hours = (self.hours + 1) & 0x1F;
+		if self.hours != 24 {
+			return;
+		}
+		self.hours = 0;
+
+		self.days += 1;
+		if self.days > 0x1FF {
+			self.days = 0;
+			self.carry = true;
+		}
+	}
+
+	pub fn to_footer(&self, timestamp: u64) -> Vec<u8> {
+		let live = [
+			self.seconds,
+			self.minutes,
+			self.hours,
+			self.days as u8,
+			self.day_high(),
+		];
+		let mut footer = Vec::with_capacity(FOOTER_SIZE);
+
+		for value in live.iter().chain(self.latched.iter()) {
+			footer.extend_from_slice(&(*value as u32).to_le_bytes());
+		}
+		footer.extend_from_slice(&timestamp.to_le_bytes());
+
+		footer
+	}
+
+	// Restore the clock from a save footer, returns the timestamp it was written at
+	pub fn load_footer(&mut self, footer: &[u8]) -> Option<u64> {
+		if footer.len() != FOOTER_SIZE && footer.len() != LEGACY_FOOTER_SIZE {
+			return None;
+		}
+
+		let value = |index: usize| footer[index * 4];
+
+		self.seconds = value(0) & 0x3F;
+		self.minutes = value(1) & 0x3F;
+		self.hours = value(2) & 0x1F;
+		self.days = value(3) as u16 | ((value(4) as u16 & 0x01) << 8);
+		self.halt = value(4) & 0x40 != 0;
+		self.carry = value(4) & 0x80 != 0;
+		for (index, latched) in self.latched.iter_mut().enumerate() {
+			*latched = value(5 + index);
+		}
+		self.sub_cycles = 0;
+
+		let mut timestamp = [0u8; 8];
+		let timestamp_size = footer.len() - 40;
+		timestamp[..timestamp_size].copy_from_slice(&footer[40..]);
+
+		Some(u64::from_le_bytes(timestamp))
+	}
+}
@@ -1,104 +1,279 @@
-use log::{debug, info};
-use std::fs::File;
-use std::io::{BufReader, Read};
+#![allow(dead_code)]
+use log::{debug, info, warn};
+use serde_json::Value;
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 mod common;
+pub mod error;
+pub mod header;
+mod mapper;
 
-use common::cartridge_destination::get_destination;
-use common::cartridge_type::get_cartridge_type;
-use common::licensee_codes::{get_old_publisher, get_publisher};
-use common::ram_size::get_ram_size;
-use common::rom_size::get_rom_size;
+use error::{CartridgeError, CartridgeWarning};
+use header::{CartridgeHeader, CartridgeType};
+use mapper::{get_mapper, Mapper};
 
 #[derive(Debug)]
 pub struct Cartridge {
 	pub rom: Vec<u8>,
-	pub game_title: String,
+	pub rom_path: Option<PathBuf>,
+	pub header: Option<CartridgeHeader>, // None until a ROM is loaded
+	pub mapper: Box<dyn Mapper>,
+	pub rtc_sync_host: bool, // advance the RTC by the host time elapsed since the save was written
+	pub has_battery: bool,
+	pub save_path: Option<PathBuf>, // <rom>.sav for battery backed cartridges
+	pub battery_saves: bool,        // disabled for headless runs so they don't depend on .sav files
+	ram_dirty: bool,
 }
 
 impl Cartridge {
 	pub fn new() -> Self {
 		Self {
 			rom: Vec::new(),
-			game_title: String::new(),
+			rom_path: None,
+			header: None,
+			mapper: get_mapper(&CartridgeType::from_code(0x00), 0),
+			rtc_sync_host: false,
+			has_battery: false,
+			save_path: None,
+			battery_saves: true,
+			ram_dirty: false,
 		}
 	}
 
-	pub fn load_rom(&mut self, rom_path: String) {
+	// Load a ROM image, header problems that don't prevent running it are returned as warnings
+	pub fn load_rom(&mut self, rom_path: String) -> Result<Vec<CartridgeWarning>, CartridgeError> {
 		debug!("Starting Cartridge...");
 
-		let file = File::open(&rom_path).expect("ROM not found!");
-		let file_size = file.metadata().expect("Failed to get file metadata.").len() as usize;
+		let file = File::open(&rom_path)?;
+		let file_size = file.metadata()?.len() as usize;
 		let mut buffer = BufReader::new(file);
-
-		self.rom.resize(file_size, 0x00);
+		let mut rom = vec![0x00; file_size];
 
 		debug!("Reading ROM: {}", &rom_path);
-		buffer
-			.read_exact(&mut self.rom)
-			.expect("Failed to read ROM.");
+		buffer.read_exact(&mut rom)?;
 		debug!("ROM Read Successfully!");
 
-		// Game Title
-		self.game_title = String::from_utf8_lossy(&self.rom[0x134..=0x143]).to_string();
-		info!("Game Title: {}", self.game_title);
-
-		// Game Version
-		let game_version = self.rom[0x14C].clone().to_string();
-		info!("Game Version: {}.0", game_version);
+		let header = CartridgeHeader::parse(&rom)?;
 
-		// Licensee Codes
-		let licensee_code = &self.rom[0x14b];
+		// Flush the save of the previous game before replacing it
+		if let Err(e) = self.save() {
+			warn!("Failed to write save file: {}", e);
+		}
 
-		if *licensee_code == 0x33 {
-			let new_lic_code = String::from_utf8_lossy(&self.rom[0x144..=0x145]).to_string();
-			info!("New Licensee Code: {}", get_publisher(new_lic_code));
-		} else {
-			info!("Old Licensee Code: {}", get_old_publisher(*licensee_code as u16))
+		for line in header.to_string().lines() {
+			info!("{}", line);
 		}
 
-		// Cartridge Type
-		let cartridge_type_code = &self.rom[0x147];
-		info!("Cartridge Type: {}", get_cartridge_type(*cartridge_type_code));
+		self.rom = rom;
+		self.rom_path = Some(PathBuf::from(&rom_path));
 
-		// ROM Size
-		let rom_size_code = &self.rom[0x148];
-		info!("ROM Size: {}", get_rom_size(*rom_size_code));
+		// Memory Bank Controller
+		self.mapper = get_mapper(&header.cartridge_type, header.ram_size);
 
-		// RAM Size
-		let ram_size_code = &self.rom[0x149];
-		info!("RAM Size: {}", get_ram_size(*ram_size_code));
+		// Battery Save
+		self.has_battery = header.has_battery();
+		self.save_path = if self.has_battery && self.battery_saves {
+			Some(Path::new(&rom_path).with_extension("sav"))
+		} else {
+			None
+		};
+		self.ram_dirty = false;
 
-		// Cartridge Destination
-		let destination_code = &self.rom[0x14A];
-		info!("Cartridge Destination: {}", get_destination(*destination_code));
+		if let Err(e) = self.load_save() {
+			warn!("Failed to read save file: {}", e);
+		}
 
-		// Checksum
-		let header_checksum = &self.rom[0x14D];
-		let mut checksum: u8 = 0;
+		self.header = Some(header);
 
-		for addr in 0x134..=0x14C {
-			checksum = checksum.wrapping_sub(self.rom[addr]).wrapping_sub(1);
+		// The callers report them, only log them along with the header
+		let warnings = self.validate_header();
+		for warning in &warnings {
+			info!("{}", warning);
 		}
 
-		info!(
-			"Checksum: {:02X} ({})",
-			checksum,
-			if *header_checksum == checksum {
-				"PASSED"
-			} else {
-				"FAILED"
-			}
-		);
-
-		// panic!("stop");
+		Ok(warnings)
+	}
+
+	fn validate_header(&self) -> Vec<CartridgeWarning> {
+		match &self.header {
+			Some(header) => header.validate(self.rom.len()),
+			None => Vec::new(),
+		}
 	}
 
 	pub fn read(&self, addr: u16) -> u8 {
-		return self.rom[addr as usize];
+		match addr {
+			0x0000..=0x7FFF => self.mapper.read_rom(&self.rom, addr),
+			0xA000..=0xBFFF => self.mapper.read_ram(addr),
+			_ => 0xFF,
+		}
 	}
 
 	pub fn write(&mut self, addr: u16, data: u8) {
-		self.rom[addr as usize] = data;
+		match addr {
+			// Writes to the ROM area go to the mapper registers
+			0x0000..=0x7FFF => self.mapper.write_rom(addr, data),
+			// Only writes the mapper accepted need a save
+			0xA000..=0xBFFF => self.ram_dirty |= self.mapper.write_ram(addr, data),
+			_ => (),
+		}
+	}
+
+	// Advance the cartridge clock (MBC3 RTC) by emulated T-cycles
+	pub fn step(&mut self, cycles: u32) {
+		self.mapper.step(cycles);
+	}
+
+	pub fn rumble(&self) -> bool {
+		self.mapper.rumble()
+	}
+
+	// Mapper registers, RAM and RTC for save states, the ROM itself isn't part of them
+	pub fn save_state(&self) -> serde_json::Result<Value> {
+		self.mapper.save_state()
+	}
+
+	pub fn load_state(&mut self, state: Value) -> serde_json::Result<()> {
+		self.mapper.load_state(state)?;
+		self.ram_dirty = true;
+
+		Ok(())
+	}
+
+	// Catch the RTC up with the host clock, `saved_at` is the UNIX timestamp stored with the save
+	pub fn sync_rtc(&mut self, saved_at: u64) {
+		if !self.rtc_sync_host {
+			return;
+		}
+
+		let now = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.map(|time| time.as_secs())
+			.unwrap_or(saved_at);
+
+		if let Some(rtc) = self.mapper.rtc() {
+			rtc.advance_seconds(now.saturating_sub(saved_at));
+		}
+	}
+
+	// Load external RAM (and the RTC footer) from the .sav file, if there is one
+	fn load_save(&mut self) -> io::Result<()> {
+		let Some(path) = &self.save_path else {
+			return Ok(());
+		};
+
+		if !path.exists() {
+			return Ok(());
+		}
+
+		let data = fs::read(path)?;
+		let ram = self.mapper.ram_mut();
+		let ram_size = ram.len().min(data.len());
+		ram[..ram_size].copy_from_slice(&data[..ram_size]);
+		info!("Save Loaded: {}", path.display());
+
+		let footer = &data[ram_size..];
+		let saved_at = match self.mapper.rtc() {
+			Some(rtc) => rtc.load_footer(footer),
+			None => None,
+		};
+
+		if let Some(saved_at) = saved_at {
+			self.sync_rtc(saved_at);
+		}
+
+		Ok(())
+	}
+
+	// Write external RAM (and the RTC footer) to the .sav file through a temp file
+	pub fn save(&mut self) -> io::Result<()> {
+		let Some(path) = self.save_path.clone() else {
+			return Ok(());
+		};
+
+		let mut data = self.mapper.ram().to_vec();
+
+		if let Some(rtc) = self.mapper.rtc() {
+			let now = SystemTime::now()
+				.duration_since(UNIX_EPOCH)
+				.map(|time| time.as_secs())
+				.unwrap_or(0);
+			data.extend_from_slice(&rtc.to_footer(now));
+		}
+
+		if data.is_empty() {
+			return Ok(());
+		}
+
+		// Renaming is atomic, a crash mid-write leaves the old save intact
+		let temp_path = path.with_extension("sav.tmp");
+		fs::write(&temp_path, &data)?;
+		fs::rename(&temp_path, &path)?;
+
+		self.ram_dirty = false;
+		debug!("Save Written: {}", path.display());
+
+		Ok(())
+	}
+
+	// Periodic save, skipped when nothing changed (the RTC always changes)
+	pub fn save_if_dirty(&mut self) -> io::Result<()> {
+		if self.ram_dirty || self.mapper.rtc().is_some() {
+			return self.save();
+		}
+
+		Ok(())
+	}
+}
+
+// Writes a blank ROM with the given cartridge type and RAM size code, for tests
+#[cfg(test)]
+pub fn write_test_rom(name: &str, cartridge_type: u8, ram_size_code: u8) -> PathBuf {
+	let mut rom = vec![0x00; 0x8000];
+	rom[0x104..0x134].copy_from_slice(&header::NINTENDO_LOGO);
+	rom[0x134..0x134 + name.len().min(15)].copy_from_slice(&name.as_bytes()[..name.len().min(15)]);
+	rom[0x147] = cartridge_type;
+	rom[0x149] = ram_size_code;
+
+	let path = std::env::temp_dir().join(format!("emuchan_{}_{}.gb", name, std::process::id()));
+	fs::write(&path, &rom).unwrap();
+	let _ = fs::remove_file(path.with_extension("sav"));
+
+	path
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn battery_ram_survives_a_reload() {
+		let rom_path = write_test_rom("battery_reload", 0x1B, 0x03); // MBC5+RAM+BATTERY, 32 KiB
+		let rom_name = rom_path.to_string_lossy().to_string();
+
+		let mut cartridge = Cartridge::new();
+		cartridge.load_rom(rom_name.clone()).unwrap();
+
+		// Writes while the RAM is disabled are dropped and don't need a save
+		cartridge.write(0xA000, 0x11);
+		assert!(!cartridge.ram_dirty);
+
+		cartridge.write(0x0000, 0x0A); // enable RAM
+		cartridge.write(0x4000, 0x02); // RAM bank 2
+		cartridge.write(0xA123, 0x42);
+		assert!(cartridge.ram_dirty);
+		cartridge.save().unwrap();
+
+		let mut reloaded = Cartridge::new();
+		reloaded.load_rom(rom_name).unwrap();
+		reloaded.write(0x0000, 0x0A);
+		reloaded.write(0x4000, 0x02);
+		assert_eq!(reloaded.read(0xA123), 0x42);
+
+		let _ = fs::remove_file(rom_path.with_extension("sav"));
+		let _ = fs::remove_file(rom_path);
 	}
 }
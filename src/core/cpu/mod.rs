@@ -1,4 +1,5 @@
 #![allow(dead_code)]
+use serde::{Deserialize, Serialize};
 use std::{
 	fmt,
 	sync::{Arc, Mutex},
@@ -11,12 +12,33 @@ use opcodes::Opcode;
 use register::{Flags, Register};
 
 use crate::core::bus::BUS;
+use crate::core::interrupt::Interrupt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum CPUState {
+	Running,
+	Halted,  // HALT: waits for a pending interrupt
+	Stopped, // STOP: waits for joypad input
+}
+
+// CPU state without the bus connection, for save states
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CPUSnapshot {
+	pub reg: Register,
+	pub cycles: usize,
+	pub state: CPUState,
+	pub ime_scheduled: bool,
+	pub halt_bug: bool,
+}
 
 #[derive(Debug)]
 pub struct CPU {
 	pub reg: Register,
 	bus: Arc<Mutex<BUS>>,
 	pub cycles: usize,
+	pub state: CPUState,
+	pub ime_scheduled: bool, // EI enables IME only after the next instruction
+	pub halt_bug: bool,      // next fetch does not increment PC
 }
 
 impl CPU {
@@ -25,17 +47,38 @@ impl CPU {
 			reg: Register::new(),
 			bus,
 			cycles: 0,
+			state: CPUState::Running,
+			ime_scheduled: false,
+			halt_bug: false,
 		}
 	}
 
+	pub fn snapshot(&self) -> CPUSnapshot {
+		CPUSnapshot {
+			reg: self.reg.clone(),
+			cycles: self.cycles,
+			state: self.state,
+			ime_scheduled: self.ime_scheduled,
+			halt_bug: self.halt_bug,
+		}
+	}
+
+	pub fn restore(&mut self, snapshot: CPUSnapshot) {
+		self.reg = snapshot.reg;
+		self.cycles = snapshot.cycles;
+		self.state = snapshot.state;
+		self.ime_scheduled = snapshot.ime_scheduled;
+		self.halt_bug = snapshot.halt_bug;
+	}
+
 	pub fn read(&mut self, addr: u16) -> u8 {
-		let bus = self.bus.lock().unwrap();
-		bus.read(addr)
+		let mut bus = self.bus.lock().unwrap();
+		bus.cpu_read(addr)
 	}
 
 	pub fn write(&mut self, addr: u16, data: u8) {
 		let mut bus = self.bus.lock().unwrap();
-		bus.write(addr, data);
+		bus.cpu_write(addr, data);
 	}
 
 	pub fn view_memory_at(&self, memory: &[u8], address: usize, n: usize) {
@@ -60,13 +103,20 @@ impl CPU {
 
 	pub fn fetch(&mut self) -> u8 {
 		let data = self.read(self.reg.pc);
+
+		if self.halt_bug {
+			// HALT bug: the byte after HALT is read twice
+			self.halt_bug = false;
+			return data;
+		}
+
 		self.reg.pc = self.reg.pc.wrapping_add(1);
 
 		data
 	}
 	pub fn fetch16(&mut self) -> u16 {
 		let lo = self.read(self.reg.pc);
-		let hi = self.read(self.reg.pc + 1);
+		let hi = self.read(self.reg.pc.wrapping_add(1));
 		self.reg.pc = self.reg.pc.wrapping_add(2);
 		let data = ((hi as u16) << 8) | lo as u16;
 		data
@@ -94,14 +144,80 @@ impl CPU {
 		data
 	}
 
+	// Interrupts that are both requested (IF) and enabled (IE)
+	pub fn pending_interrupts(&mut self) -> u8 {
+		let ie = self.read(0xFFFF);
+		let flag = self.read(0xFF0F);
+		ie & flag & 0x1F
+	}
+
+	// Service the highest priority pending interrupt, returns the cycles spent on dispatch
+	fn handle_interrupts(&mut self) -> Option<u32> {
+		let pending = self.pending_interrupts();
+
+		if pending == 0 {
+			return None;
+		}
+
+		// A pending interrupt always wakes the CPU up from HALT, even with IME disabled
+		let was_halted = self.state == CPUState::Halted;
+		self.state = CPUState::Running;
+
+		if self.reg.ime == 0 {
+			return None;
+		}
+
+		let flag = self.read(0xFF0F);
+
+		let interrupt = pending & pending.wrapping_neg();
+
+		self.reg.ime = 0;
+		self.write(0xFF0F, flag & !interrupt);
+		self.push(self.reg.pc);
+		self.reg.pc = Interrupt::vector(interrupt);
+
+		// Leaving HALT costs one extra M-cycle
+		Some(if was_halted { 24 } else { 20 })
+	}
+
 	pub fn step(&mut self) -> Result<u32, String> {
+		if self.state == CPUState::Stopped {
+			// Any selected button pulls one of the P1 input lines low
+			if self.read(0xFF00) & 0x0F != 0x0F {
+				self.state = CPUState::Running;
+			}
+			return Ok(4);
+		}
+
+		self.bus.lock().unwrap().start_instruction();
+
+		if let Some(cycles) = self.handle_interrupts() {
+			return Ok(cycles);
+		}
+
+		if self.state == CPUState::Halted {
+			// Keep the clock running so the PPU and timer are still ticked
+			return Ok(4);
+		}
+
+		let enable_ime = self.ime_scheduled;
+
+		// The IE/IF checks above aren't part of the instruction timing
+		self.bus.lock().unwrap().start_instruction();
 		let instruction = self.fetch();
 		let mut opcode = Opcode::new(self);
 
-		match opcode.decode(instruction) {
-			Err(e) => return Err(format!("ERROR: {}", e)),
-			Ok(_) => return Ok(self.cycles as u32),
+		if let Err(e) = opcode.decode(instruction) {
+			return Err(format!("ERROR: {}", e));
+		}
+
+		// EI takes effect after the instruction that follows it (unless a DI cancelled it)
+		if enable_ime && self.ime_scheduled {
+			self.reg.ime = 1;
+			self.ime_scheduled = false;
 		}
+
+		Ok(self.cycles as u32)
 	}
 }
 
@@ -1,6 +1,6 @@
 use crate::core::cpu::{
 	register::{Flags, Register16, Register8},
-	CPU,
+	CPUState, CPU,
 };
 
 #[derive(Clone, Copy)]
@@ -11,6 +11,13 @@ pub enum Condition {
 	C,
 }
 
+// 8-bit operand decoded from the 3-bit code, (HL) is the memory pointed by HL
+#[derive(Clone, Copy)]
+pub enum Operand8 {
+	Reg(Register8),
+	HL,
+}
+
 pub struct Opcode<'a> {
 	cpu: &'a mut CPU,
 }
@@ -23,80 +30,133 @@ impl<'a> Opcode<'a> {
 	pub fn decode(&mut self, instruction: u8) -> Result<(), String> {
 		match instruction {
 			0x00 => self.nop(),
+			0x10 => self.stop(),
+			0x76 => self.halt(),
+
+			0xF3 => self.di(),
+			0xFB => self.ei(),
 
 			0x06 => self.ld_r8_imm8(Register8::B),
 			0x0E => self.ld_r8_imm8(Register8::C),
 			0x16 => self.ld_r8_imm8(Register8::D),
 			0x1E => self.ld_r8_imm8(Register8::E),
+			0x26 => self.ld_r8_imm8(Register8::H),
 			0x2E => self.ld_r8_imm8(Register8::L),
+			0x36 => self.ld_into_hl_imm8(),
 			0x3E => self.ld_r8_imm8(Register8::A),
 
-			0x4F => self.ld_r8_r8(Register8::C, Register8::A),
-			0x57 => self.ld_r8_r8(Register8::D, Register8::A),
-			0x67 => self.ld_r8_r8(Register8::H, Register8::A),
-			0x78 => self.ld_r8_r8(Register8::A, Register8::B),
-			0x7B => self.ld_r8_r8(Register8::A, Register8::E),
-			0x7C => self.ld_r8_r8(Register8::A, Register8::H),
-			0x7D => self.ld_r8_r8(Register8::A, Register8::L),
+			// LD r, r / LD r, (HL) / LD (HL), r
+			0x40..=0x7F => self.ld_block(instruction)?,
 
+			0x0A => self.ld_a_from_addr_r16(Register16::BC),
 			0x1A => self.ld_a_from_addr_r16(Register16::DE),
+			0x2A => self.ld_a_from_hli(),
+			0x3A => self.ld_a_from_hld(),
 			0xF0 => self.ld_a_from_addr_ff00_plus_imm8(),
+			0xF2 => self.ld_a_from_addr_ff00_plus_c(),
+			0xFA => self.ld_a_from_addr_u16(),
 
+			0x02 => self.ld_into_addr_r16_from_a(Register16::BC),
+			0x12 => self.ld_into_addr_r16_from_a(Register16::DE),
 			0x22 => self.ld_a_into_hli(),
 			0x32 => self.ld_a_into_hld(),
-			0x77 => self.ld_into_hl_r8(Register8::A),
 			0xE0 => self.ld_into_ff00_plus_u8_reg_a(),
 			0xE2 => self.ld_to_addr_ff00_plus_c_from_a(),
 			0xEA => self.ld_to_addr_u16_from_a(),
 
+			0x01 => self.ld_r16_imm16(Register16::BC),
 			0x11 => self.ld_r16_imm16(Register16::DE),
 			0x21 => self.ld_r16_imm16(Register16::HL),
 			0x31 => self.ld_r16_imm16(Register16::SP),
+			0x08 => self.ld_to_addr_u16_from_sp(),
+			0xF8 => self.ld_hl_sp_plus_imm8(),
+			0xF9 => self.ld_sp_hl(),
 
 			0x04 => self.inc_r8(Register8::B),
 			0x0C => self.inc_r8(Register8::C),
+			0x14 => self.inc_r8(Register8::D),
+			0x1C => self.inc_r8(Register8::E),
 			0x24 => self.inc_r8(Register8::H),
+			0x2C => self.inc_r8(Register8::L),
+			0x34 => self.inc_addr_hl(),
+			0x3C => self.inc_r8(Register8::A),
 
 			0x05 => self.dec_r8(Register8::B),
 			0x0D => self.dec_r8(Register8::C),
 			0x15 => self.dec_r8(Register8::D),
 			0x1D => self.dec_r8(Register8::E),
+			0x25 => self.dec_r8(Register8::H),
+			0x2D => self.dec_r8(Register8::L),
+			0x35 => self.dec_addr_hl(),
 			0x3D => self.dec_r8(Register8::A),
 
-			0xAF => self.xor_r8_r8(Register8::A, Register8::A),
-
-			0x86 => {
-				let addr = self.cpu.reg.get_r16(Register16::HL);
-				let value = self.cpu.read(addr);
-				self.add_a(value);
-				self.cpu.set_cycles(4);
-			}
-			0x90 => {
-				let value = self.cpu.reg.get_r8(Register8::B);
-				self.sub_a(value);
-			}
-			0xBE => {
-				let addr = self.cpu.reg.get_r16(Register16::HL);
-				let value = self.cpu.read(addr);
-				self.cp_a(value);
-				self.cpu.set_cycles(8);
-			}
-			0xFE => {
-				let value = self.cpu.fetch();
-				self.cp_a(value);
-				self.cpu.set_cycles(8);
-			}
-
+			0x03 => self.inc_r16(Register16::BC),
 			0x13 => self.inc_r16(Register16::DE),
 			0x23 => self.inc_r16(Register16::HL),
-
+			0x33 => self.inc_r16(Register16::SP),
+
+			0x0B => self.dec_r16(Register16::BC),
+			0x1B => self.dec_r16(Register16::DE),
+			0x2B => self.dec_r16(Register16::HL),
+			0x3B => self.dec_r16(Register16::SP),
+
+			0x09 => self.add_hl_r16(Register16::BC),
+			0x19 => self.add_hl_r16(Register16::DE),
+			0x29 => self.add_hl_r16(Register16::HL),
+			0x39 => self.add_hl_r16(Register16::SP),
+			0xE8 => self.add_sp_imm8(),
+
+			// ADD/ADC/SUB/SBC/AND/XOR/OR/CP A, r / A, (HL)
+			0x80..=0xBF => self.alu_block(instruction)?,
+
+			0xC6 => self.alu_a_imm8(Self::add_a),
+			0xCE => self.alu_a_imm8(Self::adc_a),
+			0xD6 => self.alu_a_imm8(Self::sub_a),
+			0xDE => self.alu_a_imm8(Self::sbc_a),
+			0xE6 => self.alu_a_imm8(Self::and_a),
+			0xEE => self.alu_a_imm8(Self::xor_a),
+			0xF6 => self.alu_a_imm8(Self::or_a),
+			0xFE => self.alu_a_imm8(Self::cp_a),
+
+			0x07 => self.rlca(),
+			0x0F => self.rrca(),
 			0x17 => self.rla(),
+			0x1F => self.rra(),
+
+			0x27 => self.daa(),
+			0x2F => self.cpl(),
+			0x37 => self.scf(),
+			0x3F => self.ccf(),
 
 			0xC5 => self.push_r16(Register16::BC),
+			0xD5 => self.push_r16(Register16::DE),
+			0xE5 => self.push_r16(Register16::HL),
+			0xF5 => self.push_r16(Register16::AF),
+
 			0xC1 => self.pop_r16(Register16::BC),
+			0xD1 => self.pop_r16(Register16::DE),
+			0xE1 => self.pop_r16(Register16::HL),
+			0xF1 => self.pop_r16(Register16::AF),
 
 			0xCD => self.call_imm16(),
+			0xC4 => self.call_cc_imm16(Condition::NZ),
+			0xCC => self.call_cc_imm16(Condition::Z),
+			0xD4 => self.call_cc_imm16(Condition::NC),
+			0xDC => self.call_cc_imm16(Condition::C),
+
 			0xC9 => self.ret(),
+			0xD9 => self.reti(),
+			0xC0 => self.ret_cc(Condition::NZ),
+			0xC8 => self.ret_cc(Condition::Z),
+			0xD0 => self.ret_cc(Condition::NC),
+			0xD8 => self.ret_cc(Condition::C),
+
+			0xC3 => self.jp_imm16(),
+			0xE9 => self.jp_hl(),
+			0xC2 => self.jp_cc_imm16(Condition::NZ),
+			0xCA => self.jp_cc_imm16(Condition::Z),
+			0xD2 => self.jp_cc_imm16(Condition::NC),
+			0xDA => self.jp_cc_imm16(Condition::C),
 
 			0x18 => self.jr_imm8(),
 			0x20 => self.jr_cc_imm8(Condition::NZ),
@@ -104,82 +164,248 @@ impl<'a> Opcode<'a> {
 			0x30 => self.jr_cc_imm8(Condition::NC),
 			0x38 => self.jr_cc_imm8(Condition::C),
 
-			0xC0 => self.ret_cc(Condition::NZ),
-			0xC8 => self.ret_cc(Condition::Z),
-			0xD0 => self.ret_cc(Condition::NC),
-			0xD8 => self.ret_cc(Condition::C),
+			0xC7 => self.rst(0x00),
+			0xCF => self.rst(0x08),
+			0xD7 => self.rst(0x10),
+			0xDF => self.rst(0x18),
+			0xE7 => self.rst(0x20),
+			0xEF => self.rst(0x28),
+			0xF7 => self.rst(0x30),
+			0xFF => self.rst(0x38),
 
 			0xCB => self.decode_cb()?,
 
+			// D3, DB, DD, E3, E4, EB, EC, ED, F4, FC and FD hard-lock the real CPU
 			_ => return Err(format!("Unknow instruction. OPCODE: {:02X}", instruction)),
 		}
 
 		Ok(())
 	}
 
+	// Decoder for the 0x40..=0x7F block (LD r, r). HALT (0x76) sits in the middle and is matched before.
+	fn ld_block(&mut self, instruction: u8) -> Result<(), String> {
+		let dst = self.decode_register8((instruction >> 3) & 0b111)?;
+		let src = self.decode_register8(instruction & 0b111)?;
+
+		match (dst, src) {
+			(Operand8::HL, Operand8::Reg(src)) => self.ld_into_hl_r8(src),
+			(Operand8::Reg(dst), Operand8::HL) => self.ld_r8_from_hl(dst),
+			(Operand8::Reg(dst), Operand8::Reg(src)) => self.ld_r8_r8(dst, src),
+			(Operand8::HL, Operand8::HL) => unreachable!("0x76 is HALT"),
+		}
+
+		Ok(())
+	}
+
+	// Decoder for the 0x80..=0xBF block (8-bit ALU operations on register A)
+	fn alu_block(&mut self, instruction: u8) -> Result<(), String> {
+		let operation = (instruction >> 3) & 0b111;
+		let src = self.decode_register8(instruction & 0b111)?;
+		let value = self.read_operand8(src);
+
+		match operation {
+			0 => self.add_a(value),
+			1 => self.adc_a(value),
+			2 => self.sub_a(value),
+			3 => self.sbc_a(value),
+			4 => self.and_a(value),
+			5 => self.xor_a(value),
+			6 => self.or_a(value),
+			_ => self.cp_a(value),
+		}
+
+		// (HL) operand costs one extra memory read
+		self
+			.cpu
+			.set_cycles(if let Operand8::HL = src { 8 } else { 4 });
+
+		Ok(())
+	}
+
 	// Dispatcher for 0xCBxx instructions
 	fn decode_cb(&mut self) -> Result<(), String> {
 		let cb_instruction = self.cpu.fetch();
-		match cb_instruction {
-			// BIT family b, r
-			0x40..=0x7F => {
-				// Decode the bit and register from the opcode
-				let bit = (cb_instruction - 0x40) / 8;
-				let reg_code = cb_instruction % 8;
-				let reg = self.decode_register8(reg_code)?;
-				self.op_cb_bit(bit, reg);
-			}
 
-			// RL Family r
-			0x10..=0x17 => {
-				let reg_code = cb_instruction % 8;
-				let reg = self.decode_register8(reg_code)?;
-				self.op_cb_rl(reg);
-			}
+		// Decode the bit and the operand from the opcode
+		let bit = (cb_instruction >> 3) & 0b111;
+		let operand = self.decode_register8(cb_instruction & 0b111)?;
 
-			_ => return Err(format!("Unknow CB instruction. OPCODE: {:02X}", cb_instruction)),
+		match cb_instruction {
+			0x00..=0x07 => self.op_cb_rlc(operand),
+			0x08..=0x0F => self.op_cb_rrc(operand),
+			0x10..=0x17 => self.op_cb_rl(operand),
+			0x18..=0x1F => self.op_cb_rr(operand),
+			0x20..=0x27 => self.op_cb_sla(operand),
+			0x28..=0x2F => self.op_cb_sra(operand),
+			0x30..=0x37 => self.op_cb_swap(operand),
+			0x38..=0x3F => self.op_cb_srl(operand),
+			0x40..=0x7F => self.op_cb_bit(bit, operand),
+			0x80..=0xBF => self.op_cb_res(bit, operand),
+			0xC0..=0xFF => self.op_cb_set(bit, operand),
 		}
+
 		Ok(())
 	}
 
 	// Helper function to decode the register from the 3-bit code
-	fn decode_register8(&self, code: u8) -> Result<Register8, String> {
+	fn decode_register8(&self, code: u8) -> Result<Operand8, String> {
 		match code {
-			0 => Ok(Register8::B),
-			1 => Ok(Register8::C),
-			2 => Ok(Register8::D),
-			3 => Ok(Register8::E),
-			4 => Ok(Register8::H),
-			5 => Ok(Register8::L),
-			// 6 is (HL), treated separately
-			7 => Ok(Register8::A),
+			0 => Ok(Operand8::Reg(Register8::B)),
+			1 => Ok(Operand8::Reg(Register8::C)),
+			2 => Ok(Operand8::Reg(Register8::D)),
+			3 => Ok(Operand8::Reg(Register8::E)),
+			4 => Ok(Operand8::Reg(Register8::H)),
+			5 => Ok(Operand8::Reg(Register8::L)),
+			6 => Ok(Operand8::HL),
+			7 => Ok(Operand8::Reg(Register8::A)),
 			_ => Err("Invalid register code".to_string()),
 		}
 	}
 
-	fn op_cb_bit(&mut self, bit: u8, reg: Register8) {
-		let value = self.cpu.reg.get_r8(reg);
+	fn read_operand8(&mut self, operand: Operand8) -> u8 {
+		match operand {
+			Operand8::Reg(reg) => self.cpu.reg.get_r8(reg),
+			Operand8::HL => {
+				let addr = self.cpu.reg.get_r16(Register16::HL);
+				self.cpu.read(addr)
+			}
+		}
+	}
+
+	fn write_operand8(&mut self, operand: Operand8, value: u8) {
+		match operand {
+			Operand8::Reg(reg) => self.cpu.reg.set_r8(reg, value),
+			Operand8::HL => {
+				let addr = self.cpu.reg.get_r16(Register16::HL);
+				self.cpu.write(addr, value);
+			}
+		}
+	}
+
+	// Read-modify-write CB instructions take 8 cycles, or 16 with (HL) (read + write)
+	fn set_cb_cycles(&mut self, operand: Operand8) {
+		self
+			.cpu
+			.set_cycles(if let Operand8::HL = operand { 16 } else { 8 });
+	}
+
+	// Common flags for the rotate and shift family
+	fn set_cb_shift_flags(&mut self, result: u8, carry: bool) {
+		self.cpu.reg.set_flag(Flags::Z, result == 0);
+		self.cpu.reg.set_flag(Flags::N, false);
+		self.cpu.reg.set_flag(Flags::H, false);
+		self.cpu.reg.set_flag(Flags::C, carry);
+	}
+
+	fn op_cb_bit(&mut self, bit: u8, operand: Operand8) {
+		let value = self.read_operand8(operand);
 		let result = (value >> bit) & 1;
 
 		self.cpu.reg.set_flag(Flags::Z, result == 0);
 		self.cpu.reg.set_flag(Flags::N, false);
 		self.cpu.reg.set_flag(Flags::H, true);
-		self.cpu.set_cycles(8);
+		// BIT only reads (HL), so it is 4 cycles cheaper than the others
+		self
+			.cpu
+			.set_cycles(if let Operand8::HL = operand { 12 } else { 8 });
+	}
+
+	fn op_cb_res(&mut self, bit: u8, operand: Operand8) {
+		let value = self.read_operand8(operand);
+		self.write_operand8(operand, value & !(1 << bit));
+		self.set_cb_cycles(operand);
+	}
+
+	fn op_cb_set(&mut self, bit: u8, operand: Operand8) {
+		let value = self.read_operand8(operand);
+		self.write_operand8(operand, value | (1 << bit));
+		self.set_cb_cycles(operand);
+	}
+
+	// RLC - Rotates left, bit 7 goes to Carry and bit 0
+	fn op_cb_rlc(&mut self, operand: Operand8) {
+		let value = self.read_operand8(operand);
+		let result = value.rotate_left(1);
+		self.write_operand8(operand, result);
+
+		self.set_cb_shift_flags(result, (value >> 7) & 1 == 1);
+		self.set_cb_cycles(operand);
+	}
+
+	// RRC - Rotates right, bit 0 goes to Carry and bit 7
+	fn op_cb_rrc(&mut self, operand: Operand8) {
+		let value = self.read_operand8(operand);
+		let result = value.rotate_right(1);
+		self.write_operand8(operand, result);
+
+		self.set_cb_shift_flags(result, value & 1 == 1);
+		self.set_cb_cycles(operand);
 	}
 
-	fn op_cb_rl(&mut self, reg: Register8) {
-		let value = self.cpu.reg.get_r8(reg);
+	// RL - Rotates left via Carry
+	fn op_cb_rl(&mut self, operand: Operand8) {
+		let value = self.read_operand8(operand);
 		let carry = self.cpu.reg.get_flag(Flags::C);
 		let new_carry = (value >> 7) & 1;
 
 		let result = (value << 1) | carry;
-		self.cpu.reg.set_r8(reg, result);
+		self.write_operand8(operand, result);
 
-		self.cpu.reg.set_flag(Flags::Z, result == 0);
-		self.cpu.reg.set_flag(Flags::N, false);
-		self.cpu.reg.set_flag(Flags::H, false);
-		self.cpu.reg.set_flag(Flags::C, new_carry == 1);
-		self.cpu.set_cycles(8);
+		self.set_cb_shift_flags(result, new_carry == 1);
+		self.set_cb_cycles(operand);
+	}
+
+	// RR - Rotates right via Carry
+	fn op_cb_rr(&mut self, operand: Operand8) {
+		let value = self.read_operand8(operand);
+		let carry = self.cpu.reg.get_flag(Flags::C);
+		let new_carry = value & 1;
+
+		let result = (value >> 1) | (carry << 7);
+		self.write_operand8(operand, result);
+
+		self.set_cb_shift_flags(result, new_carry == 1);
+		self.set_cb_cycles(operand);
+	}
+
+	// SLA - Shift left arithmetic, bit 0 is reset
+	fn op_cb_sla(&mut self, operand: Operand8) {
+		let value = self.read_operand8(operand);
+		let result = value << 1;
+		self.write_operand8(operand, result);
+
+		self.set_cb_shift_flags(result, (value >> 7) & 1 == 1);
+		self.set_cb_cycles(operand);
+	}
+
+	// SRA - Shift right arithmetic, bit 7 is kept
+	fn op_cb_sra(&mut self, operand: Operand8) {
+		let value = self.read_operand8(operand);
+		let result = (value >> 1) | (value & 0x80);
+		self.write_operand8(operand, result);
+
+		self.set_cb_shift_flags(result, value & 1 == 1);
+		self.set_cb_cycles(operand);
+	}
+
+	// SWAP - Exchanges the upper and lower nibbles
+	fn op_cb_swap(&mut self, operand: Operand8) {
+		let value = self.read_operand8(operand);
+		let result = value.rotate_left(4);
+		self.write_operand8(operand, result);
+
+		self.set_cb_shift_flags(result, false);
+		self.set_cb_cycles(operand);
+	}
+
+	// SRL - Shift right logic, bit 7 is reset
+	fn op_cb_srl(&mut self, operand: Operand8) {
+		let value = self.read_operand8(operand);
+		let result = value >> 1;
+		self.write_operand8(operand, result);
+
+		self.set_cb_shift_flags(result, value & 1 == 1);
+		self.set_cb_cycles(operand);
 	}
 
 	fn check_condition(&self, cond: Condition) -> bool {
@@ -205,6 +431,14 @@ impl<'a> Opcode<'a> {
 		self.cpu.set_cycles(4);
 	}
 
+	// load into a register the value at address HL (LD r, (HL))
+	fn ld_r8_from_hl(&mut self, reg: Register8) {
+		let addr = self.cpu.reg.get_r16(Register16::HL);
+		let data = self.cpu.read(addr);
+		self.cpu.reg.set_r8(reg, data);
+		self.cpu.set_cycles(8);
+	}
+
 	// load into register A a value obtained from an address formed by a 16-bit register
 	fn ld_a_from_addr_r16(&mut self, reg: Register16) {
 		let addr = self.cpu.reg.get_r16(reg);
@@ -221,12 +455,49 @@ impl<'a> Opcode<'a> {
 		self.cpu.set_cycles(12);
 	}
 
+	// load into register A a value obtained from 0xFF00+C
+	fn ld_a_from_addr_ff00_plus_c(&mut self) {
+		let addr = ((0xFF << 8) as u16) | self.cpu.reg.c as u16;
+		self.cpu.reg.a = self.cpu.read(addr);
+		self.cpu.set_cycles(8);
+	}
+
+	// load into register A a value obtained from an immediate 16-bits address
+	fn ld_a_from_addr_u16(&mut self) {
+		let addr = self.cpu.fetch16();
+		self.cpu.reg.a = self.cpu.read(addr);
+		self.cpu.set_cycles(16);
+	}
+
+	// load into register A the value at address HL, after incremente HL
+	fn ld_a_from_hli(&mut self) {
+		let addr = self.cpu.reg.get_r16(Register16::HL);
+		self.cpu.reg.a = self.cpu.read(addr);
+		self.cpu.reg.set_r16(Register16::HL, addr.wrapping_add(1));
+		self.cpu.set_cycles(8);
+	}
+
+	// load into register A the value at address HL, after decremente HL
+	fn ld_a_from_hld(&mut self) {
+		let addr = self.cpu.reg.get_r16(Register16::HL);
+		self.cpu.reg.a = self.cpu.read(addr);
+		self.cpu.reg.set_r16(Register16::HL, addr.wrapping_sub(1));
+		self.cpu.set_cycles(8);
+	}
+
+	// load into an address formed by a 16-bit register the register A
+	fn ld_into_addr_r16_from_a(&mut self, reg: Register16) {
+		let addr = self.cpu.reg.get_r16(reg);
+		self.cpu.write(addr, self.cpu.reg.a);
+		self.cpu.set_cycles(8);
+	}
+
 	// load into address HL the register A, after incremente HL
 	fn ld_a_into_hli(&mut self) {
 		let data = self.cpu.reg.a;
 		let addr = self.cpu.reg.get_r16(Register16::HL);
 		self.cpu.write(addr, data);
-		self.cpu.reg.set_r16(Register16::HL, addr + 1);
+		self.cpu.reg.set_r16(Register16::HL, addr.wrapping_add(1));
 		self.cpu.set_cycles(8);
 	}
 
@@ -235,7 +506,7 @@ impl<'a> Opcode<'a> {
 		let data = self.cpu.reg.a;
 		let addr = self.cpu.reg.get_r16(Register16::HL);
 		self.cpu.write(addr, data);
-		self.cpu.reg.set_r16(Register16::HL, addr - 1);
+		self.cpu.reg.set_r16(Register16::HL, addr.wrapping_sub(1));
 		self.cpu.set_cycles(8);
 	}
 
@@ -247,6 +518,14 @@ impl<'a> Opcode<'a> {
 		self.cpu.set_cycles(8);
 	}
 
+	// load into address HL a immediate value
+	fn ld_into_hl_imm8(&mut self) {
+		let data = self.cpu.fetch();
+		let addr = self.cpu.reg.get_r16(Register16::HL);
+		self.cpu.write(addr, data);
+		self.cpu.set_cycles(12);
+	}
+
 	// load into FF00+u8 the value of register A
 	fn ld_into_ff00_plus_u8_reg_a(&mut self) {
 		let hi = (0xFF << 8) as u16;
@@ -254,6 +533,7 @@ impl<'a> Opcode<'a> {
 		let addr = hi | lo;
 		let data = self.cpu.reg.a;
 		self.cpu.write(addr, data);
+		self.cpu.set_cycles(12);
 	}
 
 	// load into FF00+C register A
@@ -272,6 +552,15 @@ impl<'a> Opcode<'a> {
 		self.cpu.set_cycles(16);
 	}
 
+	// load addr to immediate 16-bits from SP (low byte first)
+	fn ld_to_addr_u16_from_sp(&mut self) {
+		let addr = self.cpu.fetch16();
+		let sp = self.cpu.reg.sp;
+		self.cpu.write(addr, (sp & 0xFF) as u8);
+		self.cpu.write(addr.wrapping_add(1), (sp >> 8) as u8);
+		self.cpu.set_cycles(20);
+	}
+
 	// load 16-bits value into 16-bits register
 	fn ld_r16_imm16(&mut self, reg: Register16) {
 		let data = self.cpu.fetch16();
@@ -279,33 +568,75 @@ impl<'a> Opcode<'a> {
 		self.cpu.set_cycles(12);
 	}
 
-	// incremet 8bits register
-	fn inc_r8(&mut self, reg: Register8) {
-		let before = self.cpu.reg.get_r8(reg);
+	// load into HL the value of SP plus a signed immediate value
+	fn ld_hl_sp_plus_imm8(&mut self) {
+		let result = self.sp_plus_imm8();
+		self.cpu.reg.set_r16(Register16::HL, result);
+		self.cpu.set_cycles(12);
+	}
+
+	// load HL into SP
+	fn ld_sp_hl(&mut self) {
+		self.cpu.reg.sp = self.cpu.reg.get_r16(Register16::HL);
+		self.cpu.set_cycles(8);
+	}
+
+	// Helper function for INC logic, Carry flag is not affected
+	fn inc_value(&mut self, before: u8) -> u8 {
 		let result = before.wrapping_add(1);
-		self.cpu.reg.set_r8(reg, result);
 		let hc = (before & 0xF) + (1 & 0xF);
 
 		self.cpu.reg.set_flag(Flags::Z, result == 0);
 		self.cpu.reg.set_flag(Flags::N, false);
 		self.cpu.reg.set_flag(Flags::H, hc > 0xF);
-		self.cpu.set_cycles(4);
+		result
 	}
 
-	// decrement 8bits register
-	fn dec_r8(&mut self, reg: Register8) {
-		let before = self.cpu.reg.get_r8(reg);
+	// Helper function for DEC logic, Carry flag is not affected
+	fn dec_value(&mut self, before: u8) -> u8 {
 		let result = before.wrapping_sub(1);
-		self.cpu.reg.set_r8(reg, result);
-
 		let hc = (before & 0xF) < 1;
 
 		self.cpu.reg.set_flag(Flags::Z, result == 0);
 		self.cpu.reg.set_flag(Flags::N, true);
 		self.cpu.reg.set_flag(Flags::H, hc);
+		result
+	}
+
+	// incremet 8bits register
+	fn inc_r8(&mut self, reg: Register8) {
+		let before = self.cpu.reg.get_r8(reg);
+		let result = self.inc_value(before);
+		self.cpu.reg.set_r8(reg, result);
 		self.cpu.set_cycles(4);
 	}
 
+	// decrement 8bits register
+	fn dec_r8(&mut self, reg: Register8) {
+		let before = self.cpu.reg.get_r8(reg);
+		let result = self.dec_value(before);
+		self.cpu.reg.set_r8(reg, result);
+		self.cpu.set_cycles(4);
+	}
+
+	// increment the value at address HL
+	fn inc_addr_hl(&mut self) {
+		let addr = self.cpu.reg.get_r16(Register16::HL);
+		let before = self.cpu.read(addr);
+		let result = self.inc_value(before);
+		self.cpu.write(addr, result);
+		self.cpu.set_cycles(12);
+	}
+
+	// decrement the value at address HL
+	fn dec_addr_hl(&mut self) {
+		let addr = self.cpu.reg.get_r16(Register16::HL);
+		let before = self.cpu.read(addr);
+		let result = self.dec_value(before);
+		self.cpu.write(addr, result);
+		self.cpu.set_cycles(12);
+	}
+
 	// Increment a 16-bit register
 	fn inc_r16(&mut self, reg: Register16) {
 		let value = self.cpu.reg.get_r16(reg).wrapping_add(1);
@@ -320,18 +651,55 @@ impl<'a> Opcode<'a> {
 		self.cpu.set_cycles(8);
 	}
 
-	// xor with two registers
-	fn xor_r8_r8(&mut self, reg1: Register8, reg2: Register8) {
-		let r1 = self.cpu.reg.get_r8(reg1);
-		let r2 = self.cpu.reg.get_r8(reg2);
-		let result = r1 ^ r2;
-		self.cpu.reg.set_r8(reg1, result);
+	// Add a 16-bit register into HL, Zero flag is not affected
+	fn add_hl_r16(&mut self, reg: Register16) {
+		let hl = self.cpu.reg.get_r16(Register16::HL);
+		let value = self.cpu.reg.get_r16(reg);
+		let result = hl.wrapping_add(value);
+		self.cpu.reg.set_r16(Register16::HL, result);
 
-		self.cpu.reg.set_flag(Flags::Z, result == 0);
 		self.cpu.reg.set_flag(Flags::N, false);
-		self.cpu.reg.set_flag(Flags::H, false);
-		self.cpu.reg.set_flag(Flags::C, false);
-		self.cpu.set_cycles(4);
+		self
+			.cpu
+			.reg
+			.set_flag(Flags::H, (hl & 0x0FFF) + (value & 0x0FFF) > 0x0FFF);
+		self
+			.cpu
+			.reg
+			.set_flag(Flags::C, (hl as u32) + (value as u32) > 0xFFFF);
+		self.cpu.set_cycles(8);
+	}
+
+	// Add a signed immediate value into SP
+	fn add_sp_imm8(&mut self) {
+		self.cpu.reg.sp = self.sp_plus_imm8();
+		self.cpu.set_cycles(16);
+	}
+
+	// Helper function for SP + i8 logic, flags are computed from the unsigned low byte addition
+	fn sp_plus_imm8(&mut self) -> u16 {
+		let offset = self.cpu.fetch() as i8 as u16;
+		let sp = self.cpu.reg.sp;
+
+		self.cpu.reg.set_flag(Flags::Z, false);
+		self.cpu.reg.set_flag(Flags::N, false);
+		self
+			.cpu
+			.reg
+			.set_flag(Flags::H, (sp & 0x0F) + (offset & 0x0F) > 0x0F);
+		self
+			.cpu
+			.reg
+			.set_flag(Flags::C, (sp & 0xFF) + (offset & 0xFF) > 0xFF);
+
+		sp.wrapping_add(offset)
+	}
+
+	// Apply an ALU operation to register A with an immediate value (OP A, u8)
+	fn alu_a_imm8(&mut self, operation: fn(&mut Self, u8)) {
+		let value = self.cpu.fetch();
+		operation(self, value);
+		self.cpu.set_cycles(8);
 	}
 
 	// Helper function for ADDITION logic
@@ -353,6 +721,26 @@ impl<'a> Opcode<'a> {
 		self.cpu.set_cycles(4);
 	}
 
+	// Helper function for ADDITION WITH CARRY logic
+	fn adc_a(&mut self, value: u8) {
+		let a = self.cpu.reg.a;
+		let carry = self.cpu.reg.get_flag(Flags::C);
+		let result = a.wrapping_add(value).wrapping_add(carry);
+		self.cpu.reg.a = result;
+
+		self.cpu.reg.set_flag(Flags::Z, result == 0);
+		self.cpu.reg.set_flag(Flags::N, false);
+		self
+			.cpu
+			.reg
+			.set_flag(Flags::H, (a & 0x0F) + (value & 0x0F) + carry > 0x0F);
+		self
+			.cpu
+			.reg
+			.set_flag(Flags::C, (a as u16) + (value as u16) + (carry as u16) > 0xFF);
+		self.cpu.set_cycles(4);
+	}
+
 	// Helper function for SUBTRACTION logic
 	fn sub_a(&mut self, value: u8) {
 		let a = self.cpu.reg.a;
@@ -366,6 +754,59 @@ impl<'a> Opcode<'a> {
 		self.cpu.set_cycles(4);
 	}
 
+	// Helper function for SUBTRACTION WITH CARRY logic
+	fn sbc_a(&mut self, value: u8) {
+		let a = self.cpu.reg.a;
+		let carry = self.cpu.reg.get_flag(Flags::C);
+		let result = a.wrapping_sub(value).wrapping_sub(carry);
+		self.cpu.reg.a = result;
+
+		self.cpu.reg.set_flag(Flags::Z, result == 0);
+		self.cpu.reg.set_flag(Flags::N, true);
+		self
+			.cpu
+			.reg
+			.set_flag(Flags::H, (a & 0x0F) < (value & 0x0F) + carry);
+		self
+			.cpu
+			.reg
+			.set_flag(Flags::C, (a as u16) < (value as u16) + (carry as u16));
+		self.cpu.set_cycles(4);
+	}
+
+	fn and_a(&mut self, value: u8) {
+		let result = self.cpu.reg.a & value;
+		self.cpu.reg.a = result;
+
+		self.cpu.reg.set_flag(Flags::Z, result == 0);
+		self.cpu.reg.set_flag(Flags::N, false);
+		self.cpu.reg.set_flag(Flags::H, true);
+		self.cpu.reg.set_flag(Flags::C, false);
+		self.cpu.set_cycles(4);
+	}
+
+	fn xor_a(&mut self, value: u8) {
+		let result = self.cpu.reg.a ^ value;
+		self.cpu.reg.a = result;
+
+		self.cpu.reg.set_flag(Flags::Z, result == 0);
+		self.cpu.reg.set_flag(Flags::N, false);
+		self.cpu.reg.set_flag(Flags::H, false);
+		self.cpu.reg.set_flag(Flags::C, false);
+		self.cpu.set_cycles(4);
+	}
+
+	fn or_a(&mut self, value: u8) {
+		let result = self.cpu.reg.a | value;
+		self.cpu.reg.a = result;
+
+		self.cpu.reg.set_flag(Flags::Z, result == 0);
+		self.cpu.reg.set_flag(Flags::N, false);
+		self.cpu.reg.set_flag(Flags::H, false);
+		self.cpu.reg.set_flag(Flags::C, false);
+		self.cpu.set_cycles(4);
+	}
+
 	fn cp_a(&mut self, value: u8) {
 		let a = self.cpu.reg.a;
 		let result = a.wrapping_sub(value);
@@ -377,6 +818,34 @@ impl<'a> Opcode<'a> {
 		self.cpu.set_cycles(4);
 	}
 
+	// RLCA - Rotates A to the left, bit 7 goes to Carry and bit 0
+	fn rlca(&mut self) {
+		let a = self.cpu.reg.a;
+		let new_carry = (a >> 7) & 1;
+
+		self.cpu.reg.a = a.rotate_left(1);
+
+		self.cpu.reg.set_flag(Flags::Z, false); // Z é sempre 0
+		self.cpu.reg.set_flag(Flags::N, false);
+		self.cpu.reg.set_flag(Flags::H, false);
+		self.cpu.reg.set_flag(Flags::C, new_carry == 1);
+		self.cpu.set_cycles(4);
+	}
+
+	// RRCA - Rotates A to the right, bit 0 goes to Carry and bit 7
+	fn rrca(&mut self) {
+		let a = self.cpu.reg.a;
+		let new_carry = a & 1;
+
+		self.cpu.reg.a = a.rotate_right(1);
+
+		self.cpu.reg.set_flag(Flags::Z, false); // Z é sempre 0
+		self.cpu.reg.set_flag(Flags::N, false);
+		self.cpu.reg.set_flag(Flags::H, false);
+		self.cpu.reg.set_flag(Flags::C, new_carry == 1);
+		self.cpu.set_cycles(4);
+	}
+
 	// RLA - Rotates A to the left via Carry
 	fn rla(&mut self) {
 		let a = self.cpu.reg.a;
@@ -393,11 +862,118 @@ impl<'a> Opcode<'a> {
 		self.cpu.set_cycles(4);
 	}
 
+	// RRA - Rotates A to the right via Carry
+	fn rra(&mut self) {
+		let a = self.cpu.reg.a;
+		let carry = self.cpu.reg.get_flag(Flags::C);
+		let new_carry = a & 1;
+
+		let result = (a >> 1) | (carry << 7);
+		self.cpu.reg.a = result;
+
+		self.cpu.reg.set_flag(Flags::Z, false); // Z é sempre 0
+		self.cpu.reg.set_flag(Flags::N, false);
+		self.cpu.reg.set_flag(Flags::H, false);
+		self.cpu.reg.set_flag(Flags::C, new_carry == 1);
+		self.cpu.set_cycles(4);
+	}
+
+	// DAA - Adjusts A to a valid BCD number after an addition or subtraction
+	fn daa(&mut self) {
+		let mut a = self.cpu.reg.a;
+		let mut carry = self.cpu.reg.get_flag(Flags::C) == 1;
+		let half_carry = self.cpu.reg.get_flag(Flags::H) == 1;
+
+		if self.cpu.reg.get_flag(Flags::N) == 0 {
+			if carry || a > 0x99 {
+				a = a.wrapping_add(0x60);
+				carry = true;
+			}
+			if half_carry || (a & 0x0F) > 0x09 {
+				a = a.wrapping_add(0x06);
+			}
+		} else {
+			if carry {
+				a = a.wrapping_sub(0x60);
+			}
+			if half_carry {
+				a = a.wrapping_sub(0x06);
+			}
+		}
+
+		self.cpu.reg.a = a;
+
+		self.cpu.reg.set_flag(Flags::Z, a == 0);
+		self.cpu.reg.set_flag(Flags::H, false);
+		self.cpu.reg.set_flag(Flags::C, carry);
+		self.cpu.set_cycles(4);
+	}
+
+	// CPL - Complement register A
+	fn cpl(&mut self) {
+		self.cpu.reg.a = !self.cpu.reg.a;
+
+		self.cpu.reg.set_flag(Flags::N, true);
+		self.cpu.reg.set_flag(Flags::H, true);
+		self.cpu.set_cycles(4);
+	}
+
+	// SCF - Set Carry Flag
+	fn scf(&mut self) {
+		self.cpu.reg.set_flag(Flags::N, false);
+		self.cpu.reg.set_flag(Flags::H, false);
+		self.cpu.reg.set_flag(Flags::C, true);
+		self.cpu.set_cycles(4);
+	}
+
+	// CCF - Complement Carry Flag
+	fn ccf(&mut self) {
+		let carry = self.cpu.reg.get_flag(Flags::C);
+
+		self.cpu.reg.set_flag(Flags::N, false);
+		self.cpu.reg.set_flag(Flags::H, false);
+		self.cpu.reg.set_flag(Flags::C, carry == 0);
+		self.cpu.set_cycles(4);
+	}
+
 	// NOP - No Operation
 	fn nop(&mut self) {
 		self.cpu.set_cycles(4);
 	}
 
+	// HALT - Stops executing instructions until an interrupt is pending
+	fn halt(&mut self) {
+		if self.cpu.reg.ime == 0 && self.cpu.pending_interrupts() != 0 {
+			// HALT bug: the CPU does not halt and fails to increment PC on the next fetch
+			self.cpu.halt_bug = true;
+		} else {
+			self.cpu.state = CPUState::Halted;
+		}
+		self.cpu.set_cycles(4);
+	}
+
+	// STOP - 2 bytes long, the second byte is ignored. Enters low power mode until a button is pressed
+	fn stop(&mut self) {
+		self.cpu.fetch();
+		// Writing any value resets DIV
+		self.cpu.write(0xFF04, 0x00);
+		self.cpu.state = CPUState::Stopped;
+		self.cpu.set_cycles(4);
+	}
+
+	// DI - Disable Interrupts
+	fn di(&mut self) {
+		self.cpu.reg.ime = 0;
+		self.cpu.ime_scheduled = false;
+		self.cpu.set_cycles(4);
+	}
+
+	// EI - Enable Interrupts, delayed by one instruction
+	fn ei(&mut self) {
+		self.cpu.ime_scheduled = true;
+		self.cpu.set_cycles(4);
+	}
+
 	// Relative Unconditional Jump
 	fn jr_imm8(&mut self) {
 		let offset = self.cpu.fetch() as i8;
@@ -405,6 +981,18 @@ impl<'a> Opcode<'a> {
 		self.cpu.set_cycles(12);
 	}
 
+	// Absolute Unconditional Jump
+	fn jp_imm16(&mut self) {
+		self.cpu.reg.pc = self.cpu.fetch16();
+		self.cpu.set_cycles(16);
+	}
+
+	// Jump to address HL
+	fn jp_hl(&mut self) {
+		self.cpu.reg.pc = self.cpu.reg.get_r16(Register16::HL);
+		self.cpu.set_cycles(4);
+	}
+
 	// Unconditional Subroutine Call
 	fn call_imm16(&mut self) {
 		let addr = self.cpu.fetch16();
@@ -419,6 +1007,20 @@ impl<'a> Opcode<'a> {
 		self.cpu.set_cycles(16);
 	}
 
+	// Return from an interrupt handler and enable interrupts
+	fn reti(&mut self) {
+		self.cpu.reg.pc = self.cpu.pop();
+		self.cpu.reg.ime = 1;
+		self.cpu.set_cycles(16);
+	}
+
+	// Call to one of the fixed restart vectors
+	fn rst(&mut self, vector: u16) {
+		self.cpu.push(self.cpu.reg.pc);
+		self.cpu.reg.pc = vector;
+		self.cpu.set_cycles(16);
+	}
+
 	// Push a 16-bit register onto the stack
 	fn push_r16(&mut self, reg: Register16) {
 		let value = self.cpu.reg.get_r16(reg);
@@ -444,6 +1046,29 @@ impl<'a> Opcode<'a> {
 		}
 	}
 
+	// Conditional absolute jump
+	fn jp_cc_imm16(&mut self, cond: Condition) {
+		let addr = self.cpu.fetch16();
+		if self.check_condition(cond) {
+			self.cpu.reg.pc = addr;
+			self.cpu.set_cycles(16);
+		} else {
+			self.cpu.set_cycles(12);
+		}
+	}
+
+	// Conditional subroutine call
+	fn call_cc_imm16(&mut self, cond: Condition) {
+		let addr = self.cpu.fetch16();
+		if self.check_condition(cond) {
+			self.cpu.push(self.cpu.reg.pc);
+			self.cpu.reg.pc = addr;
+			self.cpu.set_cycles(24);
+		} else {
+			self.cpu.set_cycles(12);
+		}
+	}
+
 	// Conditional return
 	fn ret_cc(&mut self, cond: Condition) {
 		// Ciclos variam um pouco, mas a lógica é essa
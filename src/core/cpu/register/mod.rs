@@ -1,3 +1,5 @@
+use serde::{Deserialize, Serialize};
+
 #[derive(Debug, Clone, Copy)]
 pub enum Register8 {
 	A,
@@ -25,7 +27,7 @@ pub enum Flags {
 	C, // Carry flag
 }
 
-#[derive(Default, Debug, Clone)]
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
 pub struct Register {
 	pub a: u8,
 	pub b: u8,
@@ -38,7 +40,6 @@ pub struct Register {
 	pub pc: u16,
 	pub sp: u16,
 	pub ime: u8,
-	pub ie: u8,
 }
 
 impl Register {
@@ -0,0 +1,78 @@
+#![allow(dead_code)]
+use serde::{Deserialize, Serialize};
+
+const OAM_SIZE: u16 = 0xA0; // 160 bytes, one byte per M-cycle
+const START_DELAY_CYCLES: u8 = 4; // the transfer starts one M-cycle after the write to FF46
+
+// OAM DMA transfer (FF46): copies XX00-XX9F into FE00-FE9F
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DMA {
+	pub register: u8, // last value written to FF46
+	pub active: bool,
+	source: u16,
+	index: u16,
+	start_delay: u8,
+	sub_cycles: u8, // T-cycles elapsed in the current M-cycle
+}
+
+impl DMA {
+	pub fn new() -> Self {
+		Self {
+			register: 0x00,
+			active: false,
+			source: 0x0000,
+			index: 0,
+			start_delay: 0,
+			sub_cycles: 0,
+		}
+	}
+
+	// A new write restarts the transfer, an in-flight one keeps blocking the bus until then
+	pub fn start(&mut self, data: u8) {
+		self.register = data;
+		// Sources above DFFF hit the echo of WRAM
+		self.source = match data {
+			0xE0..=0xFF => ((data - 0x20) as u16) << 8,
+			_ => (data as u16) << 8,
+		};
+		self.index = 0;
+		self.start_delay = START_DELAY_CYCLES;
+		self.sub_cycles = 0;
+	}
+
+	// Whether the CPU is locked out of the bus (everything but FF00-FFFF)
+	pub fn is_blocking(&self) -> bool {
+		self.active
+	}
+
+	// Advance one T-cycle, returns the (source, OAM offset) pair to copy when a byte is due
+	pub fn step(&mut self) -> Option<(u16, u16)> {
+		if self.start_delay > 0 {
+			self.start_delay -= 1;
+			if self.start_delay == 0 {
+				self.active = true;
+				self.sub_cycles = 0;
+			}
+			return None;
+		}
+
+		if !self.active {
+			return None;
+		}
+
+		self.sub_cycles += 1;
+		if self.sub_cycles < 4 {
+			return None;
+		}
+		self.sub_cycles = 0;
+
+		let transfer = (self.source.wrapping_add(self.index), self.index);
+		self.index += 1;
+
+		if self.index == OAM_SIZE {
+			self.active = false;
+		}
+
+		Some(transfer)
+	}
+}
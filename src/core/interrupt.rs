@@ -0,0 +1,77 @@
+#![allow(dead_code)]
+use serde::{Deserialize, Serialize};
+
+/*
++-----+--------+--------+----------+
+| Bit | Source | Vector | Priority |
++-----+--------+--------+----------+
+|  0  | VBlank | 0x0040 | Highest  |
+|  1  | STAT   | 0x0048 |          |
+|  2  | Timer  | 0x0050 |          |
+|  3  | Serial | 0x0058 |          |
+|  4  | Joypad | 0x0060 | Lowest   |
++-----+--------+--------+----------+
+*/
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Interrupt {
+	pub ie: u8,   // FFFF
+	pub flag: u8, // FF0F (IF)
+}
+
+impl Interrupt {
+	pub const VBLANK: u8 = 0b0000_0001;
+	pub const STAT: u8 = 0b0000_0010;
+	pub const TIMER: u8 = 0b0000_0100;
+	pub const SERIAL: u8 = 0b0000_1000;
+	pub const JOYPAD: u8 = 0b0001_0000;
+
+	pub fn new() -> Self {
+		Self {
+			ie: 0x00,
+			flag: 0x00,
+		}
+	}
+
+	pub fn read(&self, addr: u16) -> u8 {
+		match addr {
+			// upper 3 bits of IF are unused and always read as 1
+			0xFF0F => self.flag | 0xE0,
+			0xFFFF => self.ie,
+			_ => 0xFF,
+		}
+	}
+
+	pub fn write(&mut self, addr: u16, data: u8) {
+		match addr {
+			0xFF0F => self.flag = data & 0x1F,
+			0xFFFF => self.ie = data,
+			_ => (),
+		}
+	}
+
+	// Raise a request line, used by the PPU, timer, serial and joypad
+	pub fn request(&mut self, interrupt: u8) {
+		self.flag |= interrupt & 0x1F;
+	}
+
+	pub fn acknowledge(&mut self, interrupt: u8) {
+		self.flag &= !interrupt;
+	}
+
+	// Interrupts that are both requested and enabled
+	pub fn pending(&self) -> u8 {
+		self.ie & self.flag & 0x1F
+	}
+
+	pub fn vector(interrupt: u8) -> u16 {
+		match interrupt {
+			Interrupt::VBLANK => 0x0040,
+			Interrupt::STAT => 0x0048,
+			Interrupt::TIMER => 0x0050,
+			Interrupt::SERIAL => 0x0058,
+			Interrupt::JOYPAD => 0x0060,
+			_ => unreachable!("Invalid interrupt: {:08b}", interrupt),
+		}
+	}
+}
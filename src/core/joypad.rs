@@ -0,0 +1,123 @@
+#![allow(dead_code)]
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+
+use crate::core::interrupt::Interrupt;
+
+/*
+P1/JOYP (FF00) - 0 means selected/pressed
++-----+--------------------+----------------+
+| Bit | Select directions  | Select actions |
++-----+--------------------+----------------+
+|  5  |                    | Select (write) |
+|  4  | Select (write)     |                |
+|  3  | Down               | Start          |
+|  2  | Up                 | Select         |
+|  1  | Left               | B              |
+|  0  | Right              | A              |
++-----+--------------------+----------------+
+*/
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JoypadButton {
+	Right,
+	Left,
+	Up,
+	Down,
+	A,
+	B,
+	Select,
+	Start,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Joypad {
+	select: u8,     // bits 4-5 of P1
+	directions: u8, // Down, Up, Left, Right (0 = pressed)
+	actions: u8,    // Start, Select, B, A (0 = pressed)
+	#[serde(skip)]
+	pub interrupt: Option<Arc<Mutex<Interrupt>>>,
+}
+
+impl Joypad {
+	pub const SELECT_DIRECTIONS: u8 = 0b0001_0000;
+	pub const SELECT_ACTIONS: u8 = 0b0010_0000;
+
+	pub fn new() -> Self {
+		Self {
+			select: 0x30,
+			directions: 0x0F,
+			actions: 0x0F,
+			interrupt: None,
+		}
+	}
+
+	pub fn interrupt_connect(&mut self, interrupt: Arc<Mutex<Interrupt>>) {
+		self.interrupt = Some(interrupt);
+	}
+
+	pub fn read(&self) -> u8 {
+		// bits 6-7 are unused and always read as 1
+		0xC0 | self.select | self.input_lines()
+	}
+
+	pub fn write(&mut self, data: u8) {
+		// Only the select lines are writable, changing them can also pull an input line low
+		self.update_lines(|joypad| joypad.select = data & 0x30);
+	}
+
+	pub fn press(&mut self, button: JoypadButton) {
+		self.update_lines(|joypad| joypad.set_button(button, true));
+	}
+
+	pub fn release(&mut self, button: JoypadButton) {
+		self.update_lines(|joypad| joypad.set_button(button, false));
+	}
+
+	// Lower nibble of P1, the selected groups are ANDed together
+	fn input_lines(&self) -> u8 {
+		let mut lines = 0x0F;
+
+		if self.select & Joypad::SELECT_DIRECTIONS == 0 {
+			lines &= self.directions;
+		}
+
+		if self.select & Joypad::SELECT_ACTIONS == 0 {
+			lines &= self.actions;
+		}
+
+		lines
+	}
+
+	// Apply a change and request the joypad interrupt when any input line goes from high to low
+	fn update_lines(&mut self, change: impl FnOnce(&mut Self)) {
+		let before = self.input_lines();
+		change(self);
+		let after = self.input_lines();
+
+		if before & !after != 0 {
+			if let Some(int) = &self.interrupt {
+				int.lock().unwrap().request(Interrupt::JOYPAD);
+			}
+		}
+	}
+
+	fn set_button(&mut self, button: JoypadButton, pressed: bool) {
+		let (group, bit) = match button {
+			JoypadButton::Right => (&mut self.directions, 0),
+			JoypadButton::Left => (&mut self.directions, 1),
+			JoypadButton::Up => (&mut self.directions, 2),
+			JoypadButton::Down => (&mut self.directions, 3),
+			JoypadButton::A => (&mut self.actions, 0),
+			JoypadButton::B => (&mut self.actions, 1),
+			JoypadButton::Select => (&mut self.actions, 2),
+			JoypadButton::Start => (&mut self.actions, 3),
+		};
+
+		if pressed {
+			*group &= !(1 << bit);
+		} else {
+			*group |= 1 << bit;
+		}
+	}
+}
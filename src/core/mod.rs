@@ -1,4 +1,9 @@
+pub mod apu;
 pub mod bus;
 pub mod cartridge;
 pub mod cpu;
+pub mod dma;
+pub mod interrupt;
+pub mod joypad;
 pub mod ppu;
+pub mod timer;
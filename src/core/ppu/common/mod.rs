@@ -1 +1,2 @@
 pub mod pallete;
+pub mod sprite;
@@ -0,0 +1,34 @@
+use serde::{Deserialize, Serialize};
+
+// One OAM entry (4 bytes)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Sprite {
+	pub y: u8, // screen Y + 16
+	pub x: u8, // screen X + 8
+	pub tile: u8,
+	pub attributes: u8,
+	pub oam_index: u8,
+}
+
+impl Sprite {
+	pub const PALLETE: u8 = 0b0001_0000; // 0 = OBP0, 1 = OBP1
+	pub const X_FLIP: u8 = 0b0010_0000;
+	pub const Y_FLIP: u8 = 0b0100_0000;
+	pub const BG_PRIORITY: u8 = 0b1000_0000; // BG colors 1-3 are drawn over the sprite
+
+	pub fn new(oam: &[u8], oam_index: u8) -> Self {
+		let offset = oam_index as usize * 4;
+
+		Self {
+			y: oam[offset],
+			x: oam[offset + 1],
+			tile: oam[offset + 2],
+			attributes: oam[offset + 3],
+			oam_index,
+		}
+	}
+
+	pub fn is_set(&self, flag: u8) -> bool {
+		self.attributes & flag != 0
+	}
+}
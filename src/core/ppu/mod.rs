@@ -1,19 +1,28 @@
 #![allow(dead_code)]
+use serde::{Deserialize, Serialize};
 use std::fmt;
+use std::sync::{Arc, Mutex};
 
 mod common;
 mod register;
 
+use crate::common::serde_base64;
 use crate::config::GAMEBOY_RESOLUTION;
+use crate::core::interrupt::Interrupt;
 use common::pallete::Pallete;
+use common::sprite::Sprite;
 use register::{lcdc::LCDC, stat::STAT};
 
-const ACESSES_OAM_CYCLES: u32 = 20; // Mode 2 = 80 dots; 80 / 4 M-Cycle = 20
-const ACESSES_VRAM_CYCLES: u32 = 43; // Mode 3 = 172 dots; 172 / 4 M-Cycle = 43
-const HBLANK_CYCLES: u32 = 51; // Mode 0 = 204 dots; 204 / 4 M-Cycle = 51
-const VBLANK_CYCLES: u32 = 114; // Mode 1 = 4560 dots (10 scanlines) ; 4560 / 4 M-Cycle = 1140 = 114 cycles per scanline
+// step() is called once per T-cycle (1 dot), so every duration is in dots.
+// They used to be M-cycles (20/43/51/114), which ran a frame in a quarter of the 70224 T-cycles
+const ACESSES_OAM_CYCLES: u32 = 80; // Mode 2 = 80 dots
+const ACESSES_VRAM_CYCLES: u32 = 172; // Mode 3 = 172 dots
+const HBLANK_CYCLES: u32 = 204; // Mode 0 = 204 dots
+const VBLANK_CYCLES: u32 = 456; // Mode 1 = 4560 dots (10 scanlines) ; 456 dots per scanline
 
-#[derive(Debug)]
+const MAX_SPRITES_PER_LINE: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum Mode {
 	HBlank,
 	VBlank,
@@ -21,9 +30,11 @@ pub enum Mode {
 	AccessVRAM,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PPU {
+	#[serde(with = "serde_base64")]
 	pub vram: [u8; 0x2000],
+	#[serde(with = "serde_base64")]
 	pub oam: [u8; 0x00A0],
 	pub lcdc: LCDC,            // FF40
 	pub stat: STAT,            // FF41
@@ -38,8 +49,18 @@ pub struct PPU {
 	pub wx: u8,                // FF4B
 	pub mode: Mode,
 	pub cycles: u32,
+	#[serde(with = "serde_base64")]
 	pub video_buffer: [u8; (160 * 144) as usize],
 	pub current_line: u8,
+	pub line_sprites: Vec<Sprite>, // sprites selected by the OAM scan for the current line
+	#[serde(with = "serde_base64")]
+	bg_line: [u8; 160], // BG color indices (before pallete) of the current line
+	pub window_line: u8,           // internal window line counter
+	pub window_triggered: bool,    // WY matched LY at some point of this frame
+	window_full_line: bool,        // WX=166 bug, the next line is fully covered by the window
+	pub stat_line: bool, // OR of the enabled STAT sources, the interrupt fires on its rising edge
+	#[serde(skip)]
+	pub interrupt: Option<Arc<Mutex<Interrupt>>>,
 }
 
 impl PPU {
@@ -62,6 +83,13 @@ impl PPU {
 			cycles: ACESSES_OAM_CYCLES,
 			video_buffer: [0; (160 * 144) as usize],
 			current_line: 0,
+			line_sprites: Vec::with_capacity(MAX_SPRITES_PER_LINE),
+			bg_line: [0; 160],
+			window_line: 0,
+			window_triggered: false,
+			window_full_line: false,
+			stat_line: false,
+			interrupt: None,
 		}
 	}
 
@@ -80,7 +108,7 @@ impl PPU {
 
 		match addr {
 			0xFF40 => return self.lcdc.get_lcdc(),
-			0xFF41 => return self.stat.get_stat(),
+			0xFF41 => return self.stat.read_stat(),
 			0xFF42 => return self.scy,
 			0xFF43 => return self.scx,
 			0xFF44 => return self.ly,
@@ -112,12 +140,18 @@ impl PPU {
 		}
 
 		match addr {
-			0xFF40 => self.lcdc.set_lcdc(data),
-			0xFF41 => self.stat.set_stat(data),
+			0xFF40 => self.write_lcdc(data),
+			0xFF41 => {
+				self.stat.write_stat(data);
+				self.update_stat_interrupt();
+			}
 			0xFF42 => self.scy = data,
 			0xFF43 => self.scx = data,
-			0xFF44 => self.ly = data,
-			0xFF45 => self.lyc = data,
+			0xFF44 => (), // LY is read-only
+			0xFF45 => {
+				self.lyc = data;
+				self.update_coincidence();
+			}
 			0xFF47 => self.bg_pallete.set_pallete(data),
 			0xFF48 => self.obj0_pallete.set_pallete(data),
 			0xFF49 => self.obj1_pallete.set_pallete(data),
@@ -129,45 +163,271 @@ impl PPU {
 		return;
 	}
 
+	pub fn interrupt_connect(&mut self, interrupt: Arc<Mutex<Interrupt>>) {
+		self.interrupt = Some(interrupt);
+	}
+
+	fn request_interrupt(&self, interrupt: u8) {
+		if let Some(int) = &self.interrupt {
+			int.lock().unwrap().request(interrupt);
+		}
+	}
+
+	// Mode 2: select the first 10 sprites (in OAM order) that overlap the current line
+	pub fn oam_scan(&mut self) {
+		let height = if self.lcdc.is_set(LCDC::OBJ_SIZE) { 16 } else { 8 };
+		let line = self.ly as u16 + 16;
+
+		self.line_sprites.clear();
+
+		for index in 0..40 {
+			let sprite = Sprite::new(&self.oam, index);
+			let top = sprite.y as u16;
+
+			if line >= top && line < top + height {
+				self.line_sprites.push(sprite);
+
+				if self.line_sprites.len() == MAX_SPRITES_PER_LINE {
+					break;
+				}
+			}
+		}
+
+		// DMG priority: smaller X first, ties are resolved by OAM order
+		self
+			.line_sprites
+			.sort_by_key(|sprite| (sprite.x, sprite.oam_index));
+	}
+
 	pub fn draw_line(&mut self) {
+		self.draw_background();
+
+		if self.ly == self.wy {
+			self.window_triggered = true;
+		}
+
+		// On DMG the window is also hidden when the BG is disabled
+		if self.lcdc.is_set(LCDC::WINDOW_ON) && self.lcdc.is_set(LCDC::BG_ON) {
+			self.draw_window();
+		} else {
+			// The WX=166 line only follows a line that drew the window
+			self.window_full_line = false;
+		}
+
+		if self.lcdc.is_set(LCDC::OBJ_ON) {
+			self.draw_sprites();
+		}
+	}
+
+	// Color index (0-3) of a pixel inside a tile row
+	fn tile_pixel(&self, tile_data: u16, row: u8, column: u8) -> u8 {
+		let row_in_tile = row as u16 * 2;
+		let byte_1 = self.vram[(tile_data + row_in_tile) as usize];
+		let byte_2 = self.vram[(tile_data + row_in_tile + 1) as usize];
+
+		let bit_pos = 7 - column;
+		let hi = (byte_2 >> bit_pos) & 0x1;
+		let lo = (byte_1 >> bit_pos) & 0x1;
+		(hi << 1) | lo
+	}
+
+	// Address of a BG/Window tile inside vram, depends on the LCDC addressing mode
+	fn bg_tile_data(&self, tile_id: u8) -> u16 {
+		if self.lcdc.is_set(LCDC::BG_ADDR) {
+			tile_id as u16 * 16
+		} else {
+			// signed addressing, 0x9000 is tile 0
+			(0x1000 + (tile_id as i8 as i32) * 16) as u16
+		}
+	}
+
+	fn draw_background(&mut self) {
 		let bg_pallete = self.bg_pallete.extract_pallete();
+		let line_offset = self.ly as usize * GAMEBOY_RESOLUTION.width as usize;
 
-		if self.lcdc.is_set(LCDC::BG_ON) {
-			let bg_map = if self.lcdc.is_set(LCDC::BG_MAP) { 0x1C00 } else { 0x1800 };
+		if !self.lcdc.is_set(LCDC::BG_ON) {
+			// On DMG the background becomes blank (color 0)
+			self.bg_line = [0; 160];
+			for i in 0..GAMEBOY_RESOLUTION.width as usize {
+				self.video_buffer[line_offset + i] = bg_pallete[0];
+			}
+			return;
+		}
 
-			for i in 0..GAMEBOY_RESOLUTION.width {
-				let background_x = (i as u8).wrapping_add(self.scx);
-				let background_y = self.ly.wrapping_add(self.scy);
+		let bg_map = if self.lcdc.is_set(LCDC::BG_MAP) { 0x1C00 } else { 0x1800 };
 
-				let tile_map_x = (background_x / 8) as u16;
-				let tile_map_y = (background_y / 8) as u16;
+		for i in 0..GAMEBOY_RESOLUTION.width {
+			let background_x = (i as u8).wrapping_add(self.scx);
+			let background_y = self.ly.wrapping_add(self.scy);
 
-				let tile_address = bg_map + (tile_map_y * 32) + tile_map_x;
-				let tile_id = self.vram[tile_address as usize] as u16;
+			let tile_map_x = (background_x / 8) as u16;
+			let tile_map_y = (background_y / 8) as u16;
+
+			let tile_address = bg_map + (tile_map_y * 32) + tile_map_x;
+			let tile_id = self.vram[tile_address as usize];
+			let tile_data = self.bg_tile_data(tile_id);
+
+			let color_index = self.tile_pixel(tile_data, background_y % 8, background_x % 8);
+
+			self.bg_line[i as usize] = color_index;
+			self.video_buffer[line_offset + i as usize] = bg_pallete[color_index as usize];
+		}
+	}
+
+	fn draw_window(&mut self) {
+		if !self.window_triggered {
+			return;
+		}
+
+		// WX is the screen X + 7, values below 7 clip the left side of the window
+		let start_x = if self.window_full_line { 0 } else { self.wx as i16 - 7 };
+		self.window_full_line = false;
+
+		if self.wx == 166 {
+			// Only the last column is visible, but the window spans the whole next line
+			self.window_full_line = true;
+		}
 
-				let tile_data = if self.lcdc.is_set(LCDC::BG_ADDR) {
-					0x0000 + (tile_id * 16)
+		if start_x >= GAMEBOY_RESOLUTION.width as i16 {
+			return;
+		}
+
+		let bg_pallete = self.bg_pallete.extract_pallete();
+		let window_map = if self.lcdc.is_set(LCDC::WINDOW_MAP) {
+			0x1C00
+		} else {
+			0x1800
+		};
+		let line_offset = self.ly as usize * GAMEBOY_RESOLUTION.width as usize;
+		let window_y = self.window_line;
+
+		for i in start_x.max(0)..GAMEBOY_RESOLUTION.width as i16 {
+			let window_x = (i - start_x) as u8;
+
+			let tile_address = window_map + (window_y as u16 / 8) * 32 + (window_x as u16 / 8);
+			let tile_id = self.vram[tile_address as usize];
+			let tile_data = self.bg_tile_data(tile_id);
+
+			let color_index = self.tile_pixel(tile_data, window_y % 8, window_x % 8);
+
+			self.bg_line[i as usize] = color_index;
+			self.video_buffer[line_offset + i as usize] = bg_pallete[color_index as usize];
+		}
+
+		// Only advances on lines where the window was actually drawn
+		self.window_line = self.window_line.wrapping_add(1);
+	}
+
+	fn draw_sprites(&mut self) {
+		let height: u8 = if self.lcdc.is_set(LCDC::OBJ_SIZE) { 16 } else { 8 };
+		let line_offset = self.ly as usize * GAMEBOY_RESOLUTION.width as usize;
+
+		for i in 0..GAMEBOY_RESOLUTION.width as u8 {
+			let screen_x = i as u16 + 8;
+
+			// The first non transparent pixel in priority order wins, even if it is hidden by the BG
+			for sprite in &self.line_sprites {
+				let left = sprite.x as u16;
+				if screen_x < left || screen_x >= left + 8 {
+					continue;
+				}
+
+				let mut row = (self.ly as u16 + 16 - sprite.y as u16) as u8;
+				if sprite.is_set(Sprite::Y_FLIP) {
+					row = height - 1 - row;
+				}
+
+				let mut column = (screen_x - left) as u8;
+				if sprite.is_set(Sprite::X_FLIP) {
+					column = 7 - column;
+				}
+
+				// 8x16 sprites ignore bit 0 of the tile index
+				let tile = if height == 16 { sprite.tile & 0xFE } else { sprite.tile };
+				let tile_data = tile as u16 * 16;
+				let color_index = self.tile_pixel(tile_data, row, column);
+
+				// color 0 is transparent
+				if color_index == 0 {
+					continue;
+				}
+
+				if sprite.is_set(Sprite::BG_PRIORITY) && self.bg_line[i as usize] != 0 {
+					break;
+				}
+
+				let pallete = if sprite.is_set(Sprite::PALLETE) {
+					self.obj1_pallete.extract_pallete()
 				} else {
-					0x1000 + (((tile_id as i8) * 16) as u16)
+					self.obj0_pallete.extract_pallete()
 				};
 
-				let row_in_tile = (background_y % 8) * 2;
-				let byte_1 = self.vram[(tile_data + row_in_tile as u16) as usize];
-				let byte_2 = self.vram[(tile_data + row_in_tile as u16 + 1) as usize];
+				self.video_buffer[line_offset + i as usize] = pallete[color_index as usize];
+				break;
+			}
+		}
+	}
 
-				let column_in_tile = background_x % 8;
-				let bit_pos = 7 - column_in_tile;
-				let hi = (byte_2 >> bit_pos) & 0x1;
-				let lo = (byte_1 >> bit_pos) & 0x1;
-				let color_index = ((hi << 1) | lo) & 0x11;
+	fn write_lcdc(&mut self, data: u8) {
+		let was_on = self.lcdc.is_set(LCDC::LCD_ON);
+		self.lcdc.set_lcdc(data);
+		let is_on = self.lcdc.is_set(LCDC::LCD_ON);
+
+		if was_on && !is_on {
+			// Turning the LCD off resets LY and leaves STAT in mode 0
+			self.set_ly(0);
+			self.set_mode(Mode::HBlank);
+			self.window_line = 0;
+			self.window_triggered = false;
+			self.window_full_line = false;
+		} else if !was_on && is_on {
+			self.set_mode(Mode::AccessOAM);
+			self.cycles = ACESSES_OAM_CYCLES;
+			self.update_coincidence();
+		}
+	}
 
-				let color = bg_pallete[color_index as usize];
+	fn set_mode(&mut self, mode: Mode) {
+		self.mode = mode;
 
-				let pixel_index = (self.ly as usize * GAMEBOY_RESOLUTION.width as usize) + i as usize;
+		let bits = match mode {
+			Mode::HBlank => 0,
+			Mode::VBlank => 1,
+			Mode::AccessOAM => 2,
+			Mode::AccessVRAM => 3,
+		};
+		self.stat.set_mode(bits);
+		self.update_stat_interrupt();
+	}
 
-				self.video_buffer[pixel_index] = color
-			}
+	fn set_ly(&mut self, ly: u8) {
+		self.ly = ly;
+		self.update_coincidence();
+	}
+
+	// LYC == LY flag (STAT bit 2)
+	fn update_coincidence(&mut self) {
+		if self.ly == self.lyc {
+			self.stat.set(STAT::LYC_EQ_LY);
+		} else {
+			self.stat.clear(STAT::LYC_EQ_LY);
 		}
+		self.update_stat_interrupt();
+	}
+
+	// "STAT blocking": a new source going high while another one already holds the line does not interrupt
+	fn update_stat_interrupt(&mut self) {
+		let stat = &self.stat;
+		let line = (stat.is_set(STAT::LYC_INT) && stat.is_set(STAT::LYC_EQ_LY))
+			|| (stat.is_set(STAT::HBLANK_INT) && self.mode == Mode::HBlank)
+			|| (stat.is_set(STAT::VBLANK_INT) && self.mode == Mode::VBlank)
+			|| (stat.is_set(STAT::OAM_INT) && self.mode == Mode::AccessOAM);
+
+		if line && !self.stat_line {
+			self.request_interrupt(Interrupt::STAT);
+		}
+
+		self.stat_line = line;
 	}
 
 	pub fn step(&mut self) {
@@ -183,36 +443,40 @@ impl PPU {
 
 		match self.mode {
 			Mode::AccessOAM => {
-				self.mode = Mode::AccessVRAM;
+				self.oam_scan();
+				self.set_mode(Mode::AccessVRAM);
 				self.cycles = ACESSES_VRAM_CYCLES;
 			}
 
 			Mode::AccessVRAM => {
 				self.draw_line();
-				self.mode = Mode::HBlank;
+				self.set_mode(Mode::HBlank);
 				self.cycles = HBLANK_CYCLES;
 			}
 
 			Mode::HBlank => {
-				self.ly += 1;
+				self.set_ly(self.ly + 1);
 
 				if self.ly == 144 {
-					self.mode = Mode::VBlank;
+					self.set_mode(Mode::VBlank);
 					self.cycles = VBLANK_CYCLES;
+					self.request_interrupt(Interrupt::VBLANK);
 				} else {
-					self.mode = Mode::AccessOAM;
+					self.set_mode(Mode::AccessOAM);
 					self.cycles = ACESSES_OAM_CYCLES;
 				}
 			}
 
 			Mode::VBlank => {
-				self.ly += 1;
-
-				if self.ly > 153 {
-					self.ly = 0;
-					self.mode = Mode::AccessOAM;
+				if self.ly == 153 {
+					self.set_ly(0);
+					self.window_line = 0;
+					self.window_triggered = false;
+					self.window_full_line = false;
+					self.set_mode(Mode::AccessOAM);
 					self.cycles = ACESSES_OAM_CYCLES;
 				} else {
+					self.set_ly(self.ly + 1);
 					self.cycles = VBLANK_CYCLES;
 				}
 			}
@@ -302,3 +566,36 @@ impl fmt::Display for PPU {
 		writeln!(f)
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn wx_166_only_covers_the_following_window_line() {
+		let mut ppu = PPU::new();
+		ppu.write(0xFF40, 0xF1); // LCD, window (9C00 map), 8000 tile data, BG
+		ppu.write(0xFF47, 0xE4);
+		ppu.vram[0x0010..0x0020].fill(0xFF); // tile 1, color 3
+		ppu.vram[0x1C00..0x2000].fill(0x01); // window map
+		ppu.wy = 0;
+
+		ppu.wx = 166;
+		ppu.ly = 0;
+		ppu.draw_line();
+
+		// Window disabled on the next line, the WX=166 line must not carry over
+		ppu.write(0xFF40, 0xD1);
+		ppu.ly = 1;
+		ppu.draw_line();
+
+		ppu.write(0xFF40, 0xF1);
+		ppu.wx = 107;
+		ppu.ly = 2;
+		ppu.draw_line();
+
+		let line = &ppu.video_buffer[2 * 160..3 * 160];
+		assert_eq!(line[0], 0);
+		assert_eq!(line[100], 3);
+	}
+}
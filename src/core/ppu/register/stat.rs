@@ -1,4 +1,6 @@
-#[derive(Debug)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct STAT(u8);
 
 impl STAT {
@@ -17,6 +19,20 @@ impl STAT {
 		self.0 = value;
 	}
 
+	// CPU writes: bits 0-2 (mode and LYC == LY) are read-only
+	pub fn write_stat(&mut self, value: u8) {
+		self.0 = (self.0 & 0b0000_0111) | (value & 0b0111_1000);
+	}
+
+	// CPU reads: bit 7 is unused and always reads 1
+	pub fn read_stat(&self) -> u8 {
+		self.0 | 0b1000_0000
+	}
+
+	pub fn set_mode(&mut self, mode: u8) {
+		self.0 = (self.0 & !STAT::PPU_MODE) | (mode & STAT::PPU_MODE);
+	}
+
 	pub fn get_stat(&self) -> u8 {
 		self.0
 	}
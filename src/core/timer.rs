@@ -0,0 +1,152 @@
+#![allow(dead_code)]
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+
+use crate::core::interrupt::Interrupt;
+
+/*
++------+------+------------------------------------------------------+
+| Addr | Name | Notes                                                |
++------+------+------------------------------------------------------+
+| FF04 | DIV  | Upper 8 bits of the 16-bit system counter            |
+| FF05 | TIMA | Incremented on the falling edge of the selected bit  |
+| FF06 | TMA  | Reloaded into TIMA 4 cycles after an overflow        |
+| FF07 | TAC  | Bit 2: enable, Bits 0-1: clock select                |
++------+------+------------------------------------------------------+
+
+TAC clock select -> system counter bit:
+	00: bit 9 (4096 Hz)   01: bit 3 (262144 Hz)   10: bit 5 (65536 Hz)   11: bit 7 (16384 Hz)
+*/
+
+const OVERFLOW_DELAY_CYCLES: u8 = 4;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Timer {
+	pub counter: u16,    // internal system counter, DIV is the upper byte
+	pub tima: u8,        // FF05
+	pub tma: u8,         // FF06
+	pub tac: u8,         // FF07
+	overflow_cycles: u8, // cycles left until TIMA is reloaded from TMA
+	reload_cycles: u8,   // cycles left of the M-cycle in which TIMA was reloaded
+	#[serde(skip)]
+	pub interrupt: Option<Arc<Mutex<Interrupt>>>,
+}
+
+impl Timer {
+	pub fn new() -> Self {
+		Self {
+			counter: 0x0000,
+			tima: 0x00,
+			tma: 0x00,
+			tac: 0x00,
+			overflow_cycles: 0,
+			reload_cycles: 0,
+			interrupt: None,
+		}
+	}
+
+	pub fn interrupt_connect(&mut self, interrupt: Arc<Mutex<Interrupt>>) {
+		self.interrupt = Some(interrupt);
+	}
+
+	pub fn read(&self, addr: u16) -> u8 {
+		match addr {
+			0xFF04 => (self.counter >> 8) as u8,
+			0xFF05 => self.tima,
+			0xFF06 => self.tma,
+			0xFF07 => self.tac | 0xF8,
+			_ => 0xFF,
+		}
+	}
+
+	pub fn write(&mut self, addr: u16, data: u8) {
+		match addr {
+			0xFF04 => {
+				// Resetting the counter may produce a falling edge and tick TIMA
+				let before = self.timer_signal();
+				self.counter = 0;
+				self.detect_falling_edge(before);
+			}
+			0xFF05 => {
+				// Ignored in the cycle TIMA is reloaded
+				if self.reload_cycles > 0 {
+					return;
+				}
+				// Writing during the overflow delay cancels the reload
+				self.overflow_cycles = 0;
+				self.tima = data;
+			}
+			0xFF06 => {
+				self.tma = data;
+				// TMA written in the reload cycle is also copied into TIMA
+				if self.reload_cycles > 0 {
+					self.tima = data;
+				}
+			}
+			0xFF07 => {
+				let before = self.timer_signal();
+				self.tac = data & 0b111;
+				self.detect_falling_edge(before);
+			}
+			_ => (),
+		}
+	}
+
+	// Advance the timer by one T-cycle
+	pub fn step(&mut self) {
+		if self.reload_cycles > 0 {
+			self.reload_cycles -= 1;
+		}
+
+		if self.overflow_cycles > 0 {
+			self.overflow_cycles -= 1;
+
+			if self.overflow_cycles == 0 {
+				self.tima = self.tma;
+				self.reload_cycles = OVERFLOW_DELAY_CYCLES;
+				self.request_interrupt();
+			}
+		}
+
+		let before = self.timer_signal();
+		self.counter = self.counter.wrapping_add(1);
+		self.detect_falling_edge(before);
+	}
+
+	// Bit of the system counter selected by TAC
+	fn selected_bit(&self) -> u16 {
+		match self.tac & 0b11 {
+			0b00 => 1 << 9,
+			0b01 => 1 << 3,
+			0b10 => 1 << 5,
+			_ => 1 << 7,
+		}
+	}
+
+	// AND of the enable bit with the selected counter bit
+	fn timer_signal(&self) -> bool {
+		self.tac & 0b100 != 0 && self.counter & self.selected_bit() != 0
+	}
+
+	fn detect_falling_edge(&mut self, before: bool) {
+		if before && !self.timer_signal() {
+			self.increment_tima();
+		}
+	}
+
+	fn increment_tima(&mut self) {
+		let (result, overflow) = self.tima.overflowing_add(1);
+		self.tima = result;
+
+		if overflow {
+			// TIMA stays 0x00 for 4 cycles before the reload
+			self.overflow_cycles = OVERFLOW_DELAY_CYCLES;
+		}
+	}
+
+	fn request_interrupt(&self) {
+		if let Some(int) = &self.interrupt {
+			int.lock().unwrap().request(Interrupt::TIMER);
+		}
+	}
+}
@@ -1,12 +1,23 @@
 #![allow(dead_code)]
+use crate::audio::wav::WavRecorder;
+use crate::audio::{Audio, AudioOutput};
 use crate::common::boot::BOOT_DMG;
+use crate::core::apu::APU;
 use crate::core::bus::BUS;
+use crate::core::cartridge::error::{CartridgeError, CartridgeWarning};
 use crate::core::cartridge::Cartridge;
 use crate::core::cpu::CPU;
+use crate::core::interrupt::Interrupt;
+use crate::core::joypad::{Joypad, JoypadButton};
 use crate::core::ppu::PPU;
+use crate::core::timer::Timer;
+use crate::save_state::{self, Machine, SaveState, SaveStateError};
 
+use log::warn;
+use std::io;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
-use std::time::Instant;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
 #[derive(Default, Clone, PartialEq)]
 pub enum EmulationState {
@@ -34,10 +45,17 @@ pub struct EmuChan {
 	pub cpu: Arc<Mutex<CPU>>,
 	pub ppu: Arc<Mutex<PPU>>,
 	pub cartridge: Arc<Mutex<Cartridge>>,
+	pub interrupt: Arc<Mutex<Interrupt>>,
+	pub timer: Arc<Mutex<Timer>>,
+	pub joypad: Arc<Mutex<Joypad>>,
+	pub apu: Arc<Mutex<APU>>,
 	pub emulation_state: Arc<Mutex<EmulationState>>,
+	pub audio: Audio,
+	recorder: Option<WavRecorder>,
 
 	frame_count: u32,
 	last_fps_check: Instant,
+	last_battery_save: Instant,
 	pub emu_fps: f64, //
 
 	pub emu_speed_percent: f64,
@@ -49,6 +67,10 @@ impl EmuChan {
 		let cpu = Arc::new(Mutex::new(CPU::new(Arc::clone(&bus))));
 		let ppu = Arc::new(Mutex::new(PPU::new()));
 		let cartridge = Arc::new(Mutex::new(Cartridge::new()));
+		let interrupt = Arc::new(Mutex::new(Interrupt::new()));
+		let timer = Arc::new(Mutex::new(Timer::new()));
+		let joypad = Arc::new(Mutex::new(Joypad::new()));
+		let apu = Arc::new(Mutex::new(APU::new()));
 		let emulation_state = Arc::new(Mutex::new(EmulationState::PAUSED));
 
 		{
@@ -58,6 +80,26 @@ impl EmuChan {
 			bus.cartridge_connect(Arc::clone(&cartridge));
 			// Conecting ppu to bus
 			bus.ppu_connect(Arc::clone(&ppu));
+			// Conecting timer to bus
+			bus.timer_connect(Arc::clone(&timer));
+			// Conecting joypad to bus
+			bus.joypad_connect(Arc::clone(&joypad));
+			// Conecting apu to bus
+			bus.apu_connect(Arc::clone(&apu));
+			// Conecting interrupt controller to bus, ppu, timer and joypad
+			bus.interrupt_connect(Arc::clone(&interrupt));
+			ppu
+				.lock()
+				.unwrap()
+				.interrupt_connect(Arc::clone(&interrupt));
+			timer
+				.lock()
+				.unwrap()
+				.interrupt_connect(Arc::clone(&interrupt));
+			joypad
+				.lock()
+				.unwrap()
+				.interrupt_connect(Arc::clone(&interrupt));
 			// Load boot in memory
 			bus.memory[0..=255].copy_from_slice(&BOOT_DMG);
 		}
@@ -67,22 +109,201 @@ impl EmuChan {
 			cpu,
 			ppu,
 			cartridge,
+			interrupt,
+			timer,
+			joypad,
+			apu,
 			emulation_state,
+			audio: Audio::null(),
+			recorder: None,
 			frame_count: 0,
 			last_fps_check: Instant::now(),
+			last_battery_save: Instant::now(),
 			emu_fps: 0.0,
 			emu_speed_percent: 0.0,
 		}
 	}
 
-	pub fn load_rom(&mut self, path: String) {
+	pub fn load_rom(&mut self, path: String) -> Result<Vec<CartridgeWarning>, CartridgeError> {
 		let mut cartridge = self.cartridge.lock().unwrap();
 
-		cartridge.load_rom(path);
+		cartridge.load_rom(path)
 	}
 
 	pub fn get_game_title(&self) -> String {
-		self.cartridge.lock().unwrap().game_title.clone()
+		self
+			.cartridge
+			.lock()
+			.unwrap()
+			.header
+			.as_ref()
+			.map(|header| header.title.clone())
+			.unwrap_or_default()
+	}
+
+	// Advance the MBC3 clock by the host time elapsed since the save was written, applies to the next loaded ROM
+	pub fn set_rtc_sync_host(&mut self, enabled: bool) {
+		self.cartridge.lock().unwrap().rtc_sync_host = enabled;
+	}
+
+	pub fn rtc_sync_host(&self) -> bool {
+		self.cartridge.lock().unwrap().rtc_sync_host
+	}
+
+	// Rumble motor state of MBC5 rumble cartridges
+	pub fn is_rumbling(&self) -> bool {
+		self.cartridge.lock().unwrap().rumble()
+	}
+
+	// Replaces the audio backend, the default one discards the samples
+	pub fn set_audio_output(&mut self, output: Box<dyn AudioOutput>) {
+		self.audio = Audio::new(output);
+	}
+
+	// Record the APU output to a WAV file, from the next frame on
+	pub fn start_recording(&mut self, path: &Path) -> io::Result<()> {
+		self.stop_recording()?;
+		self.recorder = Some(WavRecorder::create(path)?);
+
+		Ok(())
+	}
+
+	pub fn stop_recording(&mut self) -> io::Result<()> {
+		match self.recorder.take() {
+			Some(recorder) => recorder.finish(),
+			None => Ok(()),
+		}
+	}
+
+	pub fn is_recording(&self) -> bool {
+		self.recorder.is_some()
+	}
+
+	// Snapshot the whole machine, between two frames
+	pub fn save_state(&self, path: &Path) -> Result<(), SaveStateError> {
+		let (game_title, global_checksum) = self.game_id().ok_or(SaveStateError::NoRom)?;
+
+		let machine = Machine {
+			cpu: self.cpu.lock().unwrap().snapshot(),
+			bus: Box::new(self.bus.lock().unwrap().clone()),
+			ppu: Box::new(self.ppu.lock().unwrap().clone()),
+			timer: self.timer.lock().unwrap().clone(),
+			interrupt: self.interrupt.lock().unwrap().clone(),
+			joypad: self.joypad.lock().unwrap().clone(),
+			apu: Box::new(self.apu.lock().unwrap().clone()),
+			cartridge: self.cartridge.lock().unwrap().save_state()?,
+		};
+
+		let created_at = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.map(|time| time.as_secs())
+			.unwrap_or(0);
+
+		SaveState::new(game_title, global_checksum, created_at, machine).write(path)
+	}
+
+	pub fn load_state(&mut self, path: &Path) -> Result<(), SaveStateError> {
+		let (game_title, global_checksum) = self.game_id().ok_or(SaveStateError::NoRom)?;
+		let state = SaveState::read(path)?;
+
+		if state.game_title != game_title || state.global_checksum != global_checksum {
+			return Err(SaveStateError::WrongGame {
+				found: state.game_title,
+				expected: game_title,
+			});
+		}
+
+		let machine = state.machine;
+
+		// The only part that can still fail (another mapper), so nothing is half loaded
+		self
+			.cartridge
+			.lock()
+			.unwrap()
+			.load_state(machine.cartridge)?;
+
+		self.cpu.lock().unwrap().restore(machine.cpu);
+
+		{
+			// Keep the connections to the other components
+			let mut bus = self.bus.lock().unwrap();
+			let connections = bus.clone();
+			*bus = *machine.bus;
+			bus.cartridge = connections.cartridge;
+			bus.ppu = connections.ppu;
+			bus.interrupt = connections.interrupt;
+			bus.timer = connections.timer;
+			bus.joypad = connections.joypad;
+			bus.apu = connections.apu;
+		}
+
+		{
+			let mut ppu = self.ppu.lock().unwrap();
+			let interrupt = ppu.interrupt.take();
+			*ppu = *machine.ppu;
+			ppu.interrupt = interrupt;
+		}
+
+		{
+			let mut timer = self.timer.lock().unwrap();
+			let interrupt = timer.interrupt.take();
+			*timer = machine.timer;
+			timer.interrupt = interrupt;
+		}
+
+		{
+			let mut joypad = self.joypad.lock().unwrap();
+			let interrupt = joypad.interrupt.take();
+			*joypad = machine.joypad;
+			joypad.interrupt = interrupt;
+		}
+
+		*self.interrupt.lock().unwrap() = machine.interrupt;
+
+		{
+			// Mute and solo are debugger settings, not part of the machine
+			let mut apu = self.apu.lock().unwrap();
+			let (mute, solo) = (apu.mute, apu.solo);
+			*apu = *machine.apu;
+			apu.mute = mute;
+			apu.solo = solo;
+		}
+
+		Ok(())
+	}
+
+	// Quick save slots live next to the ROM
+	pub fn state_slot_path(&self, slot: u8) -> Option<PathBuf> {
+		let cartridge = self.cartridge.lock().unwrap();
+		let rom_path = cartridge.rom_path.as_ref()?;
+
+		Some(save_state::slot_path(rom_path, slot))
+	}
+
+	pub fn save_state_slot(&self, slot: u8) -> Result<(), SaveStateError> {
+		let path = self.state_slot_path(slot).ok_or(SaveStateError::NoRom)?;
+		self.save_state(&path)
+	}
+
+	pub fn load_state_slot(&mut self, slot: u8) -> Result<(), SaveStateError> {
+		let path = self.state_slot_path(slot).ok_or(SaveStateError::NoRom)?;
+		self.load_state(&path)
+	}
+
+	// Title and global checksum of the loaded ROM
+	fn game_id(&self) -> Option<(String, u16)> {
+		let cartridge = self.cartridge.lock().unwrap();
+		let header = cartridge.header.as_ref()?;
+
+		Some((header.title.clone(), header.global_checksum))
+	}
+
+	pub fn press_button(&mut self, button: JoypadButton) {
+		self.joypad.lock().unwrap().press(button);
+	}
+
+	pub fn release_button(&mut self, button: JoypadButton) {
+		self.joypad.lock().unwrap().release(button);
 	}
 
 	pub fn get_video_buffer(&self) -> Vec<u8> {
@@ -113,12 +334,39 @@ impl EmuChan {
 
 			cycles_this_frame += cycles_executed;
 
+			self.bus.lock().unwrap().step_dma(cycles_executed);
+			self.cartridge.lock().unwrap().step(cycles_executed);
+
+			// PPU, timer and APU keep running even while the CPU is halted
 			let mut ppu = self.ppu.lock().unwrap();
+			let mut timer = self.timer.lock().unwrap();
+			let mut apu = self.apu.lock().unwrap();
 			for _ in 0..cycles_executed {
 				ppu.step();
+				timer.step();
+				apu.step(timer.counter);
 			}
 		}
 
+		// Audio
+		{
+			let mut apu = self.apu.lock().unwrap();
+			self.audio.push(&apu.playback_samples);
+
+			if let Some(recorder) = &mut self.recorder {
+				if let Err(e) = recorder.write(&apu.samples) {
+					warn!("Failed to write audio recording, stopping it: {}", e);
+
+					// Keep what was written so far playable
+					if let Some(recorder) = self.recorder.take() {
+						let _ = recorder.finish();
+					}
+				}
+			}
+			apu.samples.clear();
+			apu.playback_samples.clear();
+		}
+
 		// Calc FPS and Speed
 		const TARGET_FPS: f64 = 59.7275;
 		self.frame_count += 1;
@@ -132,5 +380,22 @@ impl EmuChan {
 			self.frame_count = 0;
 			self.last_fps_check = Instant::now();
 		}
+
+		// Periodic battery save
+		const BATTERY_SAVE_INTERVAL_SECS: u64 = 5;
+
+		if self.last_battery_save.elapsed().as_secs() >= BATTERY_SAVE_INTERVAL_SECS {
+			if let Err(e) = self.cartridge.lock().unwrap().save_if_dirty() {
+				warn!("Failed to write save file: {}", e);
+			}
+			self.last_battery_save = Instant::now();
+		}
+	}
+
+	// Flush battery backed RAM to the .sav file (e.g. on exit)
+	pub fn save_battery(&self) {
+		if let Err(e) = self.cartridge.lock().unwrap().save() {
+			warn!("Failed to write save file: {}", e);
+		}
 	}
 }
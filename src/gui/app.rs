@@ -1,11 +1,14 @@
 use crate::emuchan::{EmuChan, EmulationState};
+use crate::save_state::{SaveState, SAVE_STATE_SLOTS};
 use crate::tests::sm83::SM83;
 use eframe::egui;
+use log::warn;
 use std::path::PathBuf;
 use std::sync::mpsc::{channel, Receiver, Sender};
 use std::sync::{Arc, Mutex};
 
 use crate::gui::common::dialog::{self, open_json_test_dialog};
+use crate::gui::common::key_bindings;
 use crate::gui::common::palettes::{self, ColorPalette};
 use crate::gui::common::window_scale::WindowScale;
 
@@ -15,12 +18,17 @@ pub struct EmuChanGui {
 	displayed_title: String,
 	rom_path_sender: Sender<PathBuf>,
 	rom_path_receiver: Receiver<PathBuf>,
+	wav_path_sender: Sender<PathBuf>,
+	wav_path_receiver: Receiver<PathBuf>,
 	selected_palette: ColorPalette,
 	window_scale: WindowScale,
 	test_result_sender: Sender<String>,
 	test_result_receiver: Receiver<String>,
 	test_log: Vec<String>,
 	show_test_runner_window: bool,
+	show_audio_window: bool,
+	slot_thumbnails: Vec<Option<egui::TextureHandle>>, // index 0 is slot 1
+	thumbnails_stale: bool,
 }
 
 impl eframe::App for EmuChanGui {
@@ -33,12 +41,22 @@ impl eframe::App for EmuChanGui {
 
 		ctx.request_repaint();
 	}
+
+	fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+		let mut emulator = self.emulator.lock().unwrap();
+		emulator.save_battery();
+
+		if let Err(e) = emulator.stop_recording() {
+			warn!("Failed to write audio recording: {}", e);
+		}
+	}
 }
 
 impl EmuChanGui {
 	pub fn new(emulator: Arc<Mutex<EmuChan>>) -> Self {
 		let (sender, receiver) = channel();
 		let (test_sender, test_receiver) = channel();
+		let (wav_sender, wav_receiver) = channel();
 
 		Self {
 			emulator,
@@ -46,12 +64,17 @@ impl EmuChanGui {
 			displayed_title: "EmuChan".to_string(),
 			rom_path_sender: sender,
 			rom_path_receiver: receiver,
+			wav_path_sender: wav_sender,
+			wav_path_receiver: wav_receiver,
 			selected_palette: ColorPalette::Classic,
 			window_scale: WindowScale::X4,
 			test_result_sender: test_sender,
 			test_result_receiver: test_receiver,
 			test_log: Vec::new(),
 			show_test_runner_window: false,
+			show_audio_window: false,
+			slot_thumbnails: Vec::new(),
+			thumbnails_stale: true,
 		}
 	}
 
@@ -87,10 +110,31 @@ impl EmuChanGui {
 			println!("Selected ROM: {}", path.display());
 
 			let mut emulator = self.emulator.lock().unwrap();
-			emulator.load_rom(path.to_string_lossy().to_string());
-			*emulator.emulation_state.lock().unwrap() = EmulationState::RUNNING;
+			match emulator.load_rom(path.to_string_lossy().to_string()) {
+				Ok(warnings) => {
+					if !warnings.is_empty() {
+						let messages: Vec<String> = warnings.iter().map(|w| w.to_string()).collect();
+						dialog::show_warning_dialog("ROM Warnings", messages.join("\n"));
+					}
+					*emulator.emulation_state.lock().unwrap() = EmulationState::RUNNING;
+					self.thumbnails_stale = true;
+				}
+				Err(e) => dialog::show_error_dialog("Failed to load ROM", e.to_string()),
+			}
+		}
+
+		if self.thumbnails_stale {
+			self.refresh_slot_thumbnails(ctx);
+		}
+
+		if let Ok(path) = self.wav_path_receiver.try_recv() {
+			if let Err(e) = self.emulator.lock().unwrap().start_recording(&path) {
+				dialog::show_error_dialog("Failed to record audio", format!("{}: {}", path.display(), e));
+			}
 		}
 
+		self.handle_input(ctx);
+
 		self.emulator.lock().unwrap().run_one_frame();
 		self.update_emulator_texture(ctx);
 
@@ -99,6 +143,87 @@ impl EmuChanGui {
 		if self.show_test_runner_window {
 			self.ui_test_runner_window(ctx);
 		}
+
+		if self.show_audio_window {
+			self.ui_audio_window(ctx);
+		}
+	}
+
+	fn handle_input(&mut self, ctx: &egui::Context) {
+		let mut emulator = self.emulator.lock().unwrap();
+		let mut slot_keys = Vec::new();
+
+		ctx.input(|i| {
+			for event in &i.events {
+				if let egui::Event::Key {
+					key,
+					pressed,
+					repeat: false,
+					modifiers,
+					..
+				} = event
+				{
+					if let Some(button) = key_bindings::get_button(*key) {
+						if *pressed {
+							emulator.press_button(button);
+						} else {
+							emulator.release_button(button);
+						}
+					}
+
+					if let Some(slot) = key_bindings::get_state_slot(*key) {
+						if *pressed {
+							slot_keys.push((slot, modifiers.shift));
+						}
+					}
+				}
+			}
+		});
+		drop(emulator);
+
+		for (slot, save) in slot_keys {
+			if save {
+				self.save_state_slot(slot);
+			} else {
+				self.load_state_slot(slot);
+			}
+		}
+	}
+
+	fn save_state_slot(&mut self, slot: u8) {
+		match self.emulator.lock().unwrap().save_state_slot(slot) {
+			Ok(()) => self.thumbnails_stale = true,
+			Err(e) => dialog::show_error_dialog("Failed to save state", e.to_string()),
+		}
+	}
+
+	fn load_state_slot(&mut self, slot: u8) {
+		if let Err(e) = self.emulator.lock().unwrap().load_state_slot(slot) {
+			dialog::show_error_dialog("Failed to load state", e.to_string());
+		}
+	}
+
+	// Reads the thumbnail of every quick save slot of the loaded game
+	fn refresh_slot_thumbnails(&mut self, ctx: &egui::Context) {
+		let pallete = palettes::get_colors(self.selected_palette);
+		let emulator = self.emulator.lock().unwrap();
+
+		self.slot_thumbnails = (1..=SAVE_STATE_SLOTS)
+			.map(|slot| {
+				let path = emulator.state_slot_path(slot)?;
+				let thumbnail = SaveState::read_thumbnail(&path).ok()?;
+
+				let rgba_buffer: Vec<u8> = thumbnail
+					.iter()
+					.flat_map(|&pixel_index| pallete[pixel_index as usize & 0x03].to_array())
+					.collect();
+				let image = egui::ColorImage::from_rgba_unmultiplied([160, 144], &rgba_buffer);
+
+				Some(ctx.load_texture(format!("state_slot_{}", slot), image, egui::TextureOptions::LINEAR))
+			})
+			.collect();
+
+		self.thumbnails_stale = false;
 	}
 
 	fn ui_top_painel(&mut self, ctx: &egui::Context) {
@@ -112,6 +237,20 @@ impl EmuChanGui {
 
 					ui.separator();
 
+					let recording = self.emulator.lock().unwrap().is_recording();
+					if !recording && ui.button("Record audio...").clicked() {
+						dialog::save_wav_dialog(self.wav_path_sender.clone());
+						ui.close_menu();
+					}
+					if recording && ui.button("Stop recording").clicked() {
+						if let Err(e) = self.emulator.lock().unwrap().stop_recording() {
+							dialog::show_error_dialog("Failed to record audio", e.to_string());
+						}
+						ui.close_menu();
+					}
+
+					ui.separator();
+
 					if ui.button("Quit").clicked() {
 						ctx.send_viewport_cmd(egui::ViewportCommand::Close);
 					}
@@ -120,10 +259,17 @@ impl EmuChanGui {
 				ui.menu_button("Emulation", |ui| {
 					ui.menu_button("Video", |ui| {
 						ui.menu_button("Color Pallete", |ui| {
+							let previous_palette = self.selected_palette;
+
 							ui.radio_value(&mut self.selected_palette, ColorPalette::Default, "Default");
 							ui.radio_value(&mut self.selected_palette, ColorPalette::Classic, "Classic Green");
 							ui.radio_value(&mut self.selected_palette, ColorPalette::Greyscale, "Greyscale");
 							ui.radio_value(&mut self.selected_palette, ColorPalette::Chocolate, "Chocolate");
+
+							// Slot thumbnails are colored with the palette too
+							if self.selected_palette != previous_palette {
+								self.thumbnails_stale = true;
+							}
 						});
 						ui.separator();
 						ui.menu_button("Resolution", |ui| {
@@ -133,6 +279,66 @@ impl EmuChanGui {
 							ui.radio_value(&mut self.window_scale, WindowScale::X4, "4x (640 x 576)");
 						});
 					});
+					ui.separator();
+
+					let mut emulator = self.emulator.lock().unwrap();
+					let mut rtc_sync_host = emulator.rtc_sync_host();
+					if ui
+						.checkbox(&mut rtc_sync_host, "Sync RTC to host time")
+						.on_hover_text(
+							"Advance the cartridge clock by the time passed since the save, on the next ROM load",
+						)
+						.changed()
+					{
+						emulator.set_rtc_sync_host(rtc_sync_host);
+					}
+				});
+
+				ui.menu_button("State", |ui| {
+					for slot in 1..=SAVE_STATE_SLOTS {
+						ui.horizontal(|ui| {
+							match self.slot_thumbnails.get(slot as usize - 1) {
+								Some(Some(texture)) => {
+									ui.add(egui::Image::new(texture).fit_to_exact_size(egui::vec2(80.0, 72.0)));
+								}
+								_ => {
+									let (rect, _) =
+										ui.allocate_exact_size(egui::vec2(80.0, 72.0), egui::Sense::hover());
+									ui.painter()
+										.rect_filled(rect, 2.0, egui::Color32::from_gray(30));
+									ui.painter().text(
+										rect.center(),
+										egui::Align2::CENTER_CENTER,
+										"Empty",
+										egui::FontId::proportional(12.0),
+										egui::Color32::GRAY,
+									);
+								}
+							}
+
+							ui.vertical(|ui| {
+								ui.label(format!("Slot {}", slot));
+								ui.horizontal(|ui| {
+									if ui
+										.button("Save")
+										.on_hover_text(format!("Shift+F{}", slot))
+										.clicked()
+									{
+										self.save_state_slot(slot);
+										ui.close_menu();
+									}
+									if ui
+										.button("Load")
+										.on_hover_text(format!("F{}", slot))
+										.clicked()
+									{
+										self.load_state_slot(slot);
+										ui.close_menu();
+									}
+								});
+							});
+						});
+					}
 				});
 
 				ui.menu_button("Developer", |ui| {
@@ -140,6 +346,11 @@ impl EmuChanGui {
 						self.show_test_runner_window = true;
 						ui.close_menu();
 					}
+
+					if ui.button("Audio").clicked() {
+						self.show_audio_window = true;
+						ui.close_menu();
+					}
 				});
 			});
 		});
@@ -184,6 +395,16 @@ impl EmuChanGui {
 				ui.label(format!("Speed: {:.0}%", emu_speed));
 				ui.separator();
 				ui.label(format!("FPS: {:.1}", emu_fps));
+
+				if emulator.is_rumbling() {
+					ui.separator();
+					ui.label("Rumble");
+				}
+
+				if emulator.is_recording() {
+					ui.separator();
+					ui.label("Recording audio");
+				}
 			});
 		});
 	}
@@ -237,4 +458,92 @@ impl EmuChanGui {
 				});
 			});
 	}
+
+	fn ui_audio_window(&mut self, ctx: &egui::Context) {
+		const CHANNEL_NAMES: [&str; 4] = ["CH1 Square + Sweep", "CH2 Square", "CH3 Wave", "CH4 Noise"];
+
+		let emulator = self.emulator.lock().unwrap();
+		let mut apu = emulator.apu.lock().unwrap();
+
+		egui::Window::new("Audio")
+			.open(&mut self.show_audio_window)
+			.resizable(false)
+			.show(ctx, |ui| {
+				for (channel, name) in CHANNEL_NAMES.iter().enumerate() {
+					let state = apu.channel_state(channel);
+
+					ui.horizontal(|ui| {
+						ui.strong(*name);
+						ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+							ui.toggle_value(&mut apu.solo[channel], "Solo")
+								.on_hover_text("Playback only, recordings keep every channel");
+							ui.toggle_value(&mut apu.mute[channel], "Mute")
+								.on_hover_text("Playback only, recordings keep every channel");
+						});
+					});
+
+					draw_scope(ui, &apu.scope(channel), state.enabled);
+
+					let volume = match channel {
+						2 => ["0%", "100%", "50%", "25%"][state.volume as usize].to_string(),
+						_ => format!("{}/15", state.volume),
+					};
+					let duty = match state.duty {
+						Some(duty) => ["12.5%", "25%", "50%", "75%"][duty as usize],
+						None => "-",
+					};
+					let length = if state.length_enabled {
+						state.length.to_string()
+					} else {
+						"off".to_string()
+					};
+
+					ui.label(
+						egui::RichText::new(format!(
+							"{} | DAC {} | freq {:03X} ({:.1} Hz) | vol {} | duty {} | length {}",
+							if state.enabled { "ON " } else { "OFF" },
+							if state.dac_enabled { "on " } else { "off" },
+							state.frequency,
+							state.frequency_hz,
+							volume,
+							duty,
+							length,
+						))
+						.monospace(),
+					);
+
+					if channel < CHANNEL_NAMES.len() - 1 {
+						ui.separator();
+					}
+				}
+			});
+	}
+}
+
+// Oscilloscope trace, starting at a rising edge so periodic waves stand still
+fn draw_scope(ui: &mut egui::Ui, points: &[f32], enabled: bool) {
+	let (rect, _) = ui.allocate_exact_size(egui::vec2(360.0, 48.0), egui::Sense::hover());
+	let painter = ui.painter_at(rect);
+	painter.rect_filled(rect, 2.0, egui::Color32::from_gray(20));
+
+	let width = points.len() / 2;
+	let start = (1..width).find(|&i| points[i - 1] < points[i]).unwrap_or(0);
+
+	let color = if enabled {
+		egui::Color32::LIGHT_GREEN
+	} else {
+		egui::Color32::DARK_GRAY
+	};
+
+	let line: Vec<egui::Pos2> = points[start..start + width]
+		.iter()
+		.enumerate()
+		.map(|(i, value)| {
+			let x = rect.left() + rect.width() * i as f32 / (width - 1) as f32;
+			let y = rect.bottom() - 4.0 - (rect.height() - 8.0) * value;
+			egui::pos2(x, y)
+		})
+		.collect();
+
+	painter.add(egui::Shape::line(line, egui::Stroke::new(1.0, color)));
 }
@@ -17,6 +17,43 @@ pub fn open_rom_dialog(rom_sender: Sender<PathBuf>) {
 	});
 }
 
+pub fn save_wav_dialog(wav_sender: Sender<PathBuf>) {
+	std::thread::spawn(move || {
+		let current_directory = std::env::current_dir().unwrap_or_else(|_| ".".into());
+
+		let file_dialog = rfd::FileDialog::new()
+			.add_filter("WAV Audio", &["wav"])
+			.set_directory(&current_directory)
+			.set_file_name("recording.wav");
+
+		if let Some(path) = file_dialog.save_file() {
+			let _ = wav_sender.send(path);
+		}
+	});
+}
+
+pub fn show_error_dialog(title: &str, message: String) {
+	show_message_dialog(rfd::MessageLevel::Error, title, message);
+}
+
+pub fn show_warning_dialog(title: &str, message: String) {
+	show_message_dialog(rfd::MessageLevel::Warning, title, message);
+}
+
+// Runs on its own thread so the emulator keeps going while the dialog is open
+fn show_message_dialog(level: rfd::MessageLevel, title: &str, message: String) {
+	let title = title.to_string();
+
+	std::thread::spawn(move || {
+		rfd::MessageDialog::new()
+			.set_level(level)
+			.set_title(title)
+			.set_description(message)
+			.set_buttons(rfd::MessageButtons::Ok)
+			.show();
+	});
+}
+
 pub fn open_json_test_dialog(test_sender: Sender<String>) {
 	let current_directory = std::env::current_dir().unwrap_or_else(|_| ".".into());
 
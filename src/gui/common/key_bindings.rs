@@ -0,0 +1,29 @@
+use eframe::egui;
+
+use crate::core::joypad::JoypadButton;
+
+// Default keyboard layout
+pub fn get_button(key: egui::Key) -> Option<JoypadButton> {
+	match key {
+		egui::Key::ArrowRight => Some(JoypadButton::Right),
+		egui::Key::ArrowLeft => Some(JoypadButton::Left),
+		egui::Key::ArrowUp => Some(JoypadButton::Up),
+		egui::Key::ArrowDown => Some(JoypadButton::Down),
+		egui::Key::X => Some(JoypadButton::A),
+		egui::Key::Z => Some(JoypadButton::B),
+		egui::Key::Backspace => Some(JoypadButton::Select),
+		egui::Key::Enter => Some(JoypadButton::Start),
+		_ => None,
+	}
+}
+
+// Quick save state slots, Shift + key saves, the key alone loads
+pub fn get_state_slot(key: egui::Key) -> Option<u8> {
+	match key {
+		egui::Key::F1 => Some(1),
+		egui::Key::F2 => Some(2),
+		egui::Key::F3 => Some(3),
+		egui::Key::F4 => Some(4),
+		_ => None,
+	}
+}
@@ -1,3 +1,4 @@
 pub mod dialog;
+pub mod key_bindings;
 pub mod palettes;
 pub mod window_scale;
@@ -1,6 +1,6 @@
 use eframe::egui;
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Clone, Copy, clap::ValueEnum)]
 pub enum ColorPalette {
 	Default,
 	Classic,
@@ -0,0 +1,113 @@
+#![allow(dead_code)]
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+use crate::core::cartridge::error::{CartridgeError, CartridgeWarning};
+use crate::emuchan::{EmuChan, EmulationState};
+use crate::gui::common::palettes::{self, ColorPalette};
+
+const SCREEN_WIDTH: u32 = 160;
+const SCREEN_HEIGHT: u32 = 144;
+
+// Checked after every frame
+#[derive(Debug, Clone, Copy)]
+pub enum StopCondition {
+	StableFrames(u32), // the screen didn't change for N frames
+	MemoryEquals { addr: u16, value: u8 },
+}
+
+#[derive(Debug, PartialEq)]
+pub enum RunResult {
+	FrameLimit,    // ran every requested frame
+	ConditionMet,  // stopped early by the condition
+	CpuError(u32), // the CPU paused itself (unknown opcode), frame number
+}
+
+// Runs EmuChan without a window, for CI and regression tests
+pub struct Headless {
+	pub emuchan: EmuChan,
+	pub frames: u32, // frames executed so far
+}
+
+impl Headless {
+	pub fn new(rom_path: String) -> Result<(Self, Vec<CartridgeWarning>), CartridgeError> {
+		let mut emuchan = EmuChan::new();
+
+		// Runs must not depend on (or write) the .sav next to the ROM
+		emuchan.cartridge.lock().unwrap().battery_saves = false;
+
+		let warnings = emuchan.load_rom(rom_path)?;
+		*emuchan.emulation_state.lock().unwrap() = EmulationState::RUNNING;
+
+		Ok((Self { emuchan, frames: 0 }, warnings))
+	}
+
+	pub fn run(&mut self, max_frames: u32, condition: Option<StopCondition>) -> RunResult {
+		let mut last_hash = self.frame_hash();
+		let mut stable_frames = 0;
+
+		for _ in 0..max_frames {
+			self.emuchan.run_one_frame();
+
+			if *self.emuchan.emulation_state.lock().unwrap() != EmulationState::RUNNING {
+				return RunResult::CpuError(self.frames);
+			}
+			self.frames += 1;
+
+			match condition {
+				Some(StopCondition::StableFrames(frames)) => {
+					let hash = self.frame_hash();
+					stable_frames = if hash == last_hash { stable_frames + 1 } else { 0 };
+					last_hash = hash;
+
+					if stable_frames >= frames {
+						return RunResult::ConditionMet;
+					}
+				}
+				Some(StopCondition::MemoryEquals { addr, value })
+					if self.emuchan.bus.lock().unwrap().read(addr) == value =>
+				{
+					return RunResult::ConditionMet;
+				}
+				_ => (),
+			}
+		}
+
+		RunResult::FrameLimit
+	}
+
+	// FNV-1a over the color indexes, stable across builds and platforms
+	pub fn frame_hash(&self) -> u64 {
+		self
+			.emuchan
+			.get_video_buffer()
+			.iter()
+			.fold(0xCBF2_9CE4_8422_2325, |hash, pixel| {
+				(hash ^ *pixel as u64).wrapping_mul(0x0000_0100_0000_01B3)
+			})
+	}
+
+	pub fn save_png(&self, path: &Path, palette: ColorPalette) -> Result<(), png::EncodingError> {
+		let colors = palettes::get_colors(palette);
+		let rgb: Vec<u8> = self
+			.emuchan
+			.get_video_buffer()
+			.iter()
+			.flat_map(|&pixel| {
+				let color = colors[pixel as usize];
+				[color.r(), color.g(), color.b()]
+			})
+			.collect();
+
+		let file = File::create(path)?;
+		let mut encoder = png::Encoder::new(BufWriter::new(file), SCREEN_WIDTH, SCREEN_HEIGHT);
+		encoder.set_color(png::ColorType::Rgb);
+		encoder.set_depth(png::BitDepth::Eight);
+
+		let mut writer = encoder.write_header()?;
+		writer.write_image_data(&rgb)?;
+
+		Ok(())
+	}
+}
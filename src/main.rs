@@ -1,25 +1,34 @@
+mod audio;
 mod common;
 mod config;
 mod core;
 mod emuchan;
 mod gui;
+mod headless;
+mod save_state;
 mod tests;
 mod ui;
 
-// use common::disassembler::{disassemble, parse_from_file};
+use audio::sdl::SdlAudio;
+use common::disassembler::{disassemble, load_instructions};
 
 use eframe::egui;
-// use tests::sm83::SM83;
+use tests::sm83::SM83;
 
 use clap::{Parser, Subcommand};
-use env_logger;
+use env_logger::Env;
+use serde_json::json;
 use std::{
-	env,
+	fs, process,
 	sync::{Arc, Mutex},
 };
 
-use emuchan::EmuChan;
+use core::cartridge::error::CartridgeError;
+use core::cartridge::header::CartridgeHeader;
+use emuchan::{EmuChan, EmulationState};
 use gui::app::EmuChanGui;
+use gui::common::palettes::ColorPalette;
+use headless::{Headless, RunResult, StopCondition};
 
 #[derive(Parser)]
 #[command(
@@ -27,24 +36,31 @@ use gui::app::EmuChanGui;
 	author = "joserochadev",
 	version = "0.1.0",
 	about = "EmuChan Emulator CLI",
-	long_about = "This CLI allows you to run the emulator, execute specific tests, or disassemble a memory section."
+	long_about = "This CLI allows you to run the emulator, execute specific tests, disassemble a ROM or inspect its header."
 )]
 struct CLI {
 	#[command(subcommand)]
-	command: Commands,
+	command: Option<Commands>,
 }
 
 #[derive(Subcommand)]
 enum Commands {
-	/// Starts the emulator and runs the loaded ROM.
+	/// Starts the emulator window, optionally with a ROM loaded (default command).
 	///
 	/// Example:
 	/// ```
-	/// cargo run -- run path/to/rom.gb
+	/// cargo run -- gui path/to/rom.gb
 	/// ```
-	RUN { path: String },
+	#[command(alias = "run")]
+	GUI {
+		path: Option<String>,
 
-	/// Runs a specific test from a JSON file.
+		/// Advance the MBC3 clock by the host time elapsed since the save was written
+		#[arg(long)]
+		rtc_host_sync: bool,
+	},
+
+	/// Runs a specific test from a JSON file, exits with 1 when it fails.
 	///
 	/// Example:
 	/// ```
@@ -52,63 +68,242 @@ enum Commands {
 	/// ```
 	TEST { path: String },
 
-	/// Disassembles a section of memory and prints the instructions.
+	/// Disassembles a section of a ROM file and prints the instructions.
 	///
 	/// Example:
 	/// ```
-	/// cargo run -- disassemble 0x100 256
+	/// cargo run -- disassemble path/to/rom.gb 0x150 32
 	/// ```
-	DISASSEMBLER {
-		/// Starting memory address (e.g., 0x100)
+	#[command(alias = "disassembler")]
+	DISASSEMBLE {
+		path: String,
+
+		/// Starting ROM address (e.g., 0x150)
+		#[arg(default_value = "0x100")]
 		start: String,
 
-		/// Number of bytes to disassemble
-		length: usize,
+		/// Number of instructions to disassemble
+		#[arg(default_value_t = 32)]
+		count: usize,
 	},
-}
 
-// fn main() {
-// 	//env::set_var("RUST_LOG", "trace"); // defice RUST_LOG env
+	/// Prints the cartridge header of a ROM with the decoded fields and checksum validation.
+	///
+	/// Example:
+	/// ```
+	/// cargo run -- info path/to/rom.gb --json
+	/// ```
+	INFO {
+		path: String,
 
-// 	env_logger::init(); // Initialize logger
+		/// Print machine-readable JSON instead of text
+		#[arg(long)]
+		json: bool,
+	},
 
-// 	let cli = CLI::parse();
+	/// Runs a ROM without a window for a number of frames, for CI and regression tests.
+	///
+	/// Example:
+	/// ```
+	/// cargo run -- headless path/to/rom.gb --frames 600 --screenshot out.png --hash
+	/// ```
+	HEADLESS {
+		path: String,
 
-// 	match cli.command {
-// 		Commands::RUN { path } => {
-// 			println!("🔄 Starting the emulator...");
-// 			let mut emuchan = EmuChan::new(Some(path));
-// 			emuchan.run();
-// 		}
+		/// Maximum number of frames to run
+		#[arg(long, default_value_t = 600)]
+		frames: u32,
 
-// 		Commands::TEST { path } => {
-// 			println!("🔬 Running test: {}", path);
-// 			let mut sm83 = SM83::new();
-// 			sm83.run_test(path);
-// 		}
+		/// Stop once the screen hasn't changed for this many frames
+		#[arg(long, conflicts_with = "until_memory")]
+		until_stable: Option<u32>,
 
-// 		Commands::DISASSEMBLER { start, length } => {
-// 			let start_num = usize::from_str_radix(start.trim_start_matches("0x"), 16)
-// 				.expect("Invalid hexadecimal number");
+		/// Stop once a memory address holds a value (e.g., 0xA000=0x00)
+		#[arg(long)]
+		until_memory: Option<String>,
 
-// 			println!("🛠 Disassembling memory from 0x{:X} to 0x{:X}...", start_num, start_num + length);
+		/// Write the last frame to a PNG file
+		#[arg(long)]
+		screenshot: Option<String>,
 
-// 			let start_addr = u16::from_str_radix(start.trim_start_matches("0x"), 16)
-// 				.expect("Invalid hexadecimal number");
+		/// Palette used for the screenshot
+		#[arg(long, value_enum, default_value = "default")]
+		palette: ColorPalette,
 
-// 			let instructions = parse_from_file("./src/disassembler/instructions.json");
+		/// Print a hash of the last frame
+		#[arg(long)]
+		hash: bool,
 
-// 			let emuchan = EmuChan::new(None);
-// 			disassemble(start_addr as usize, &emuchan.bus.lock().unwrap().memory, &instructions, length);
-// 		}
-// 	}
-// }
+		/// Record the audio of the run to a WAV file
+		#[arg(long)]
+		record_audio: Option<String>,
+	},
+}
 
 fn main() -> Result<(), eframe::Error> {
-	env::set_var("RUST_LOG", "EmuChan=trace"); // defice RUST_LOG env
+	let cli = CLI::parse();
+	let command = cli.command.unwrap_or(Commands::GUI {
+		path: None,
+		rtc_host_sync: false,
+	});
+
+	// The GUI traces by default, the CLI tools only report warnings unless RUST_LOG says otherwise
+	let default_log = match command {
+		Commands::GUI { .. } => "EmuChan=trace",
+		_ => "warn",
+	};
+
+	env_logger::Builder::from_env(Env::default().default_filter_or(default_log)).init(); // Initialize logger
+
+	match command {
+		Commands::GUI {
+			path,
+			rtc_host_sync,
+		} => run_gui(path, rtc_host_sync),
+
+		Commands::TEST { path } => {
+			println!("🔬 Running test: {}", path);
+			let mut sm83 = SM83::new();
+
+			match sm83.run_test(path) {
+				Ok(()) => {
+					println!("All tests passed!");
+					Ok(())
+				}
+				Err(e) => {
+					eprintln!("{}", e);
+					process::exit(1);
+				}
+			}
+		}
+
+		Commands::DISASSEMBLE { path, start, count } => {
+			let Ok(start_addr) = usize::from_str_radix(start.trim_start_matches("0x"), 16) else {
+				eprintln!("Invalid hexadecimal address: {}", start);
+				process::exit(1);
+			};
+
+			let rom = match fs::read(&path) {
+				Ok(rom) => rom,
+				Err(e) => {
+					eprintln!("{}: {}", path, e);
+					process::exit(1);
+				}
+			};
+
+			println!("🛠 Disassembling {} instructions from 0x{:04X}...", count, start_addr);
+
+			let instructions = load_instructions();
+			disassemble(start_addr, &rom, &instructions, count);
+			Ok(())
+		}
+
+		Commands::HEADLESS {
+			path,
+			frames,
+			until_stable,
+			until_memory,
+			screenshot,
+			palette,
+			hash,
+			record_audio,
+		} => {
+			let condition = match (until_stable, until_memory) {
+				(Some(frames), _) => Some(StopCondition::StableFrames(frames)),
+				(None, Some(condition)) => match parse_memory_condition(&condition) {
+					Some(condition) => Some(condition),
+					None => {
+						eprintln!("Invalid memory condition: {} (expected ADDR=VALUE)", condition);
+						process::exit(1);
+					}
+				},
+				(None, None) => None,
+			};
 
-	env_logger::init(); // Initialize logger
+			run_headless(path, frames, condition, screenshot, palette, hash, record_audio);
+			Ok(())
+		}
 
+		Commands::INFO { path, json } => {
+			if let Err(e) = print_rom_info(&path, json) {
+				eprintln!("{}: {}", path, e);
+				process::exit(1);
+			}
+			Ok(())
+		}
+	}
+}
+
+fn run_headless(
+	path: String,
+	frames: u32,
+	condition: Option<StopCondition>,
+	screenshot: Option<String>,
+	palette: ColorPalette,
+	hash: bool,
+	record_audio: Option<String>,
+) {
+	let mut headless = match Headless::new(path.clone()) {
+		Ok((headless, warnings)) => {
+			for warning in warnings {
+				eprintln!("{}: {}", path, warning);
+			}
+			headless
+		}
+		Err(e) => {
+			eprintln!("{}: {}", path, e);
+			process::exit(1);
+		}
+	};
+
+	if let Some(record_audio) = &record_audio {
+		if let Err(e) = headless.emuchan.start_recording(record_audio.as_ref()) {
+			eprintln!("{}: {}", record_audio, e);
+			process::exit(1);
+		}
+	}
+
+	let result = headless.run(frames, condition);
+
+	if let Some(record_audio) = &record_audio {
+		if let Err(e) = headless.emuchan.stop_recording() {
+			eprintln!("{}: {}", record_audio, e);
+			process::exit(1);
+		}
+	}
+
+	if let Some(screenshot) = screenshot {
+		if let Err(e) = headless.save_png(screenshot.as_ref(), palette) {
+			eprintln!("{}: {}", screenshot, e);
+			process::exit(1);
+		}
+	}
+
+	if hash {
+		println!("{:016x}", headless.frame_hash());
+	}
+
+	match result {
+		RunResult::FrameLimit | RunResult::ConditionMet => {
+			eprintln!("Ran {} frames ({:?})", headless.frames, result);
+		}
+		RunResult::CpuError(frame) => {
+			eprintln!("CPU stopped on frame {}", frame);
+			process::exit(2);
+		}
+	}
+}
+
+// Parses ADDR=VALUE, both in hexadecimal
+fn parse_memory_condition(condition: &str) -> Option<StopCondition> {
+	let (addr, value) = condition.split_once('=')?;
+	let addr = u16::from_str_radix(addr.trim().trim_start_matches("0x"), 16).ok()?;
+	let value = u8::from_str_radix(value.trim().trim_start_matches("0x"), 16).ok()?;
+
+	Some(StopCondition::MemoryEquals { addr, value })
+}
+
+fn run_gui(rom_path: Option<String>, rtc_host_sync: bool) -> Result<(), eframe::Error> {
 	let options = eframe::NativeOptions {
 		centered: true,
 		viewport: egui::ViewportBuilder::default()
@@ -117,7 +312,129 @@ fn main() -> Result<(), eframe::Error> {
 		..Default::default()
 	};
 
-	let emu_model = Arc::new(Mutex::new(EmuChan::new()));
+	let mut emuchan = EmuChan::new();
+
+	// Has to be set before the ROM is loaded, the RTC is synced when its save is read
+	emuchan.set_rtc_sync_host(rtc_host_sync);
+
+	match SdlAudio::new() {
+		Ok(output) => emuchan.set_audio_output(Box::new(output)),
+		Err(e) => eprintln!("Audio disabled: {}", e),
+	}
+
+	if let Some(path) = rom_path {
+		match emuchan.load_rom(path.clone()) {
+			Ok(warnings) => {
+				for warning in warnings {
+					eprintln!("{}: {}", path, warning);
+				}
+				*emuchan.emulation_state.lock().unwrap() = EmulationState::RUNNING;
+			}
+			Err(e) => {
+				eprintln!("{}: {}", path, e);
+				process::exit(1);
+			}
+		}
+	}
+
+	let emu_model = Arc::new(Mutex::new(emuchan));
 
 	eframe::run_native("EmuChan", options, Box::new(|_cc| Ok(Box::new(EmuChanGui::new(emu_model)))))
 }
+
+// Only reads the ROM file, no mapper or .sav is set up
+fn print_rom_info(path: &str, json: bool) -> Result<(), CartridgeError> {
+	let rom = fs::read(path)?;
+	let header = CartridgeHeader::parse(&rom)?;
+	let warnings = header.validate(rom.len());
+
+	let status = |valid: bool| if valid { "OK" } else { "FAILED" };
+
+	if json {
+		let info = json!({
+			"path": path,
+			"title": header.title,
+			"manufacturer_code": header.manufacturer_code,
+			"cgb_flag": header.cgb_flag.description(),
+			"sgb": header.sgb,
+			"licensee": {
+				"code": header.licensee.code(),
+				"publisher": header.licensee.publisher(),
+			},
+			"cartridge_type": {
+				"code": header.cartridge_type.code,
+				"name": header.cartridge_type_name(),
+			},
+			"rom_size": {
+				"code": header.rom_size_code,
+				"bytes": header.rom_size,
+				"description": header.rom_size_name(),
+			},
+			"ram_size": {
+				"code": header.ram_size_code,
+				"bytes": header.ram_size,
+				"description": header.ram_size_name(),
+			},
+			"destination": {
+				"code": header.destination_code(),
+				"name": header.destination_name(),
+			},
+			"version": header.version,
+			"logo_valid": header.logo_valid,
+			"header_checksum": {
+				"value": header.header_checksum,
+				"computed": header.computed_header_checksum,
+				"valid": header.header_checksum_valid(),
+			},
+			"global_checksum": {
+				"value": header.global_checksum,
+				"computed": header.computed_global_checksum,
+				"valid": header.global_checksum_valid(),
+			},
+			"file_size": rom.len(),
+			"warnings": warnings.iter().map(|w| w.to_string()).collect::<Vec<_>>(),
+		});
+
+		println!("{}", serde_json::to_string_pretty(&info).unwrap());
+		return Ok(());
+	}
+
+	println!("File:             {}", path);
+	println!("Title:            {}", header.title);
+	println!("Manufacturer:     {}", header.manufacturer_code.as_deref().unwrap_or("-"));
+	println!("CGB Flag:         {}", header.cgb_flag.description());
+	println!("SGB Support:      {}", if header.sgb { "Yes" } else { "No" });
+	println!("Licensee:         {} ({})", header.licensee.code(), header.licensee.publisher());
+	println!(
+		"Cartridge Type:   {:02X} ({})",
+		header.cartridge_type.code,
+		header.cartridge_type_name()
+	);
+	println!("ROM Size:         {:02X} ({})", header.rom_size_code, header.rom_size_name());
+	println!("RAM Size:         {:02X} ({})", header.ram_size_code, header.ram_size_name());
+	println!(
+		"Destination:      {:02X} ({})",
+		header.destination_code(),
+		header.destination_name()
+	);
+	println!("Version:          {}", header.version);
+	println!("Nintendo Logo:    {}", status(header.logo_valid));
+	println!(
+		"Header Checksum:  {:02X} (computed {:02X}, {})",
+		header.header_checksum,
+		header.computed_header_checksum,
+		status(header.header_checksum_valid())
+	);
+	println!(
+		"Global Checksum:  {:04X} (computed {:04X}, {})",
+		header.global_checksum,
+		header.computed_global_checksum,
+		status(header.global_checksum_valid())
+	);
+
+	for warning in &warnings {
+		println!("Warning:          {}", warning);
+	}
+
+	Ok(())
+}
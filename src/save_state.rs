@@ -0,0 +1,179 @@
+#![allow(dead_code)]
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::common::serde_base64;
+use crate::core::apu::APU;
+use crate::core::bus::BUS;
+use crate::core::cpu::CPUSnapshot;
+use crate::core::interrupt::Interrupt;
+use crate::core::joypad::Joypad;
+use crate::core::ppu::PPU;
+use crate::core::timer::Timer;
+
+// Bump whenever a serialized struct changes, old states are rejected instead of half loaded
+pub const SAVE_STATE_VERSION: u32 = 3;
+pub const SAVE_STATE_SLOTS: u8 = 4;
+
+const SAVE_STATE_FORMAT: &str = "EmuChan save state";
+
+#[derive(Debug)]
+pub enum SaveStateError {
+	Io(io::Error),
+	Format(serde_json::Error),
+	NotSaveState,
+	Version { found: u32, expected: u32 },
+	WrongGame { found: String, expected: String },
+	NoRom,
+}
+
+impl fmt::Display for SaveStateError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			SaveStateError::Io(e) => write!(f, "Failed to access save state: {}", e),
+			SaveStateError::Format(e) => write!(f, "Save state is corrupted: {}", e),
+			SaveStateError::NotSaveState => write!(f, "Not an EmuChan save state"),
+			SaveStateError::Version { found, expected } => write!(
+				f,
+				"Save state version {} is not supported by this build of EmuChan (expected version {})",
+				found, expected
+			),
+			SaveStateError::WrongGame { found, expected } => {
+				write!(f, "Save state belongs to \"{}\", the loaded game is \"{}\"", found, expected)
+			}
+			SaveStateError::NoRom => write!(f, "No ROM loaded"),
+		}
+	}
+}
+
+impl std::error::Error for SaveStateError {}
+
+impl From<io::Error> for SaveStateError {
+	fn from(e: io::Error) -> Self {
+		SaveStateError::Io(e)
+	}
+}
+
+impl From<serde_json::Error> for SaveStateError {
+	fn from(e: serde_json::Error) -> Self {
+		SaveStateError::Format(e)
+	}
+}
+
+// Every component of the emulated Game Boy, the connections between them are rebuilt on load.
+// The large ones are boxed to keep them off the stack.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Machine {
+	pub cpu: CPUSnapshot,
+	pub bus: Box<BUS>,
+	pub ppu: Box<PPU>,
+	pub timer: Timer,
+	pub interrupt: Interrupt,
+	pub joypad: Joypad,
+	pub apu: Box<APU>,
+	pub cartridge: Value, // mapper registers, RAM and RTC
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SaveState {
+	format: String,
+	pub version: u32,
+	pub game_title: String,
+	pub global_checksum: u16, // identifies the ROM together with the title
+	pub created_at: u64,      // UNIX timestamp
+	pub machine: Machine,     // the PPU video buffer doubles as the thumbnail
+}
+
+// Read first, so a state from another version fails with a clear error instead of a parse error
+#[derive(Deserialize)]
+struct SaveStateInfo {
+	format: String,
+	version: u32,
+}
+
+// Only the last frame of the machine, the slot menu doesn't need the rest of it
+#[derive(Deserialize)]
+struct SaveStatePreview {
+	machine: MachinePreview,
+}
+
+#[derive(Deserialize)]
+struct MachinePreview {
+	ppu: PPUPreview,
+}
+
+#[derive(Deserialize)]
+struct PPUPreview {
+	#[serde(with = "serde_base64")]
+	video_buffer: Vec<u8>,
+}
+
+impl SaveState {
+	pub fn new(game_title: String, global_checksum: u16, created_at: u64, machine: Machine) -> Self {
+		Self {
+			format: SAVE_STATE_FORMAT.to_string(),
+			version: SAVE_STATE_VERSION,
+			game_title,
+			global_checksum,
+			created_at,
+			machine,
+		}
+	}
+
+	pub fn read(path: &Path) -> Result<Self, SaveStateError> {
+		let data = read_checked(path)?;
+
+		Ok(serde_json::from_slice(&data)?)
+	}
+
+	// Video buffer color indexes (160x144) of the state, without loading the whole machine
+	pub fn read_thumbnail(path: &Path) -> Result<Vec<u8>, SaveStateError> {
+		let data = read_checked(path)?;
+		let preview: SaveStatePreview = serde_json::from_slice(&data)?;
+
+		Ok(preview.machine.ppu.video_buffer)
+	}
+
+	// Written through a temp file, like the battery saves
+	pub fn write(&self, path: &Path) -> Result<(), SaveStateError> {
+		let data = serde_json::to_vec(self)?;
+
+		let mut temp_path = path.as_os_str().to_owned();
+		temp_path.push(".tmp");
+
+		fs::write(&temp_path, data)?;
+		fs::rename(&temp_path, path)?;
+
+		Ok(())
+	}
+}
+
+// Reads a save state file and checks its format and version
+fn read_checked(path: &Path) -> Result<Vec<u8>, SaveStateError> {
+	let data = fs::read(path)?;
+
+	let info: SaveStateInfo =
+		serde_json::from_slice(&data).map_err(|_| SaveStateError::NotSaveState)?;
+
+	if info.format != SAVE_STATE_FORMAT {
+		return Err(SaveStateError::NotSaveState);
+	}
+
+	if info.version != SAVE_STATE_VERSION {
+		return Err(SaveStateError::Version {
+			found: info.version,
+			expected: SAVE_STATE_VERSION,
+		});
+	}
+
+	Ok(data)
+}
+
+// <rom>.ss1, <rom>.ss2, ...
+pub fn slot_path(rom_path: &Path, slot: u8) -> PathBuf {
+	rom_path.with_extension(format!("ss{}", slot))
+}
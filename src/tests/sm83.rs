@@ -6,7 +6,7 @@ use std::io::BufReader;
 use std::sync::{Arc, Mutex};
 
 use crate::core::bus::BUS;
-use crate::core::cpu::CPU;
+use crate::core::cpu::{CPUState, CPU};
 
 #[derive(Debug, Clone, Copy, Deserialize)]
 struct RegisteState {
@@ -89,7 +89,7 @@ impl fmt::Display for Snapshot {
 }
 
 pub struct SM83 {
-	_bus: Arc<Mutex<BUS>>,
+	bus: Arc<Mutex<BUS>>,
 	cpu: CPU,
 }
 
@@ -98,7 +98,7 @@ impl SM83 {
 		let bus = Arc::new(Mutex::new(BUS::new()));
 		let cpu = CPU::new(Arc::clone(&bus));
 
-		Self { _bus: bus, cpu }
+		Self { bus, cpu }
 	}
 
 	fn inject(&mut self, intial_state: RegisteState) {
@@ -113,7 +113,8 @@ impl SM83 {
 			f,
 			h,
 			l,
-			..
+			ime,
+			ie,
 		} = intial_state;
 
 		self.cpu.reg.a = a;
@@ -126,6 +127,15 @@ impl SM83 {
 		self.cpu.reg.l = l;
 		self.cpu.reg.pc = pc;
 		self.cpu.reg.sp = sp;
+		self.cpu.reg.ime = ime;
+		self.cpu.state = CPUState::Running;
+		self.cpu.halt_bug = false;
+		self.cpu.ime_scheduled = false;
+
+		// The JSON tests never request interrupts
+		let mut bus = self.bus.lock().unwrap();
+		bus.write(0xFFFF, ie.unwrap_or(0));
+		bus.write(0xFF0F, 0);
 	}
 
 	fn retrive(&self) -> RegisteState {
@@ -140,7 +150,7 @@ impl SM83 {
 		let pc = self.cpu.reg.pc;
 		let sp = self.cpu.reg.sp;
 		let ime = self.cpu.reg.ime;
-		let ie = self.cpu.reg.ie;
+		let ie = self.bus.lock().unwrap().read(0xFFFF);
 
 		return RegisteState {
 			a,
@@ -173,7 +183,7 @@ impl SM83 {
 	}
 
 	pub fn run_test(&mut self, file_path: String) -> Result<(), String> {
-		let snapshots = load_json_test(file_path);
+		let snapshots = load_json_test(file_path)?;
 
 		for snapshot in snapshots {
 			self.inject(snapshot.initial);
@@ -212,12 +222,13 @@ impl SM83 {
 	}
 }
 
-fn load_json_test(file_path: String) -> Vec<Snapshot> {
-	let file = File::open(file_path).expect("\nErro ao abrir o arquivo JSON\nTest file not found.\n");
+fn load_json_test(file_path: String) -> Result<Vec<Snapshot>, String> {
+	let file =
+		File::open(&file_path).map_err(|e| format!("Test file not found: {} ({})", file_path, e))?;
 	let buffer = BufReader::new(file);
 
 	let snapshots: Vec<Snapshot> = serde_json::from_reader(buffer)
-		.expect("\nERRO: serde_json - can't convert json to Snapshot struct.\n");
+		.map_err(|e| format!("ERRO: serde_json - can't convert json to Snapshot struct: {}", e))?;
 
-	return snapshots;
+	return Ok(snapshots);
 }